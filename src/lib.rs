@@ -1,11 +1,13 @@
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::path::Path;
 use bevy::prelude::*;
-use plugin::{ButtonState, GergButton, GergControl, GergLabel, GergPictureBox};
+use bevy::window::WindowId;
+use plugin::{ActivateOn, ButtonState, ClickAction, GergButton, HitTest, GergControl, GergControlGroup, GergLabel, GergPictureBox};
 
 use crate::colors::parse_color;
-use crate::plugin::TextChanges;
+use crate::plugin::{AutoSize, BlocksInput, DefaultFocus, FocusNavigation, LabelLayout, TextChanges, get_label_font_size};
 
 mod colors;
 pub mod plugin;
@@ -15,7 +17,13 @@ mod shapes;
 struct GlobalSettings {
     font_name: String,
     font_size: String,
-    color: String
+    color: String,
+    label_space: String,
+    reference_resolution: String,
+    scale_mode: String,
+    safe_area: String,
+    focus_wrap: String,
+    camera: String
 }
 
 #[derive(Default)]
@@ -24,9 +32,21 @@ struct Control {
     name: String,
     fields: Fields
 }
+impl Control {
+    fn is_container(&self) -> bool {
+        let result = match self.control_type {
+            ControlType::Stack | ControlType::Grid => true,
+            _ => false
+        };
+
+        result
+    }
+}
 
 pub struct Controls {
-    map: HashMap<String, Control>
+    map: HashMap<String, Control>,
+    global_settings: GlobalSettings,
+    safe_area_override: Option<Rect<Val>>
 }
 impl Controls {
     fn get_by_name(&self, name: String) -> &Control {
@@ -48,6 +68,9 @@ impl Fields {
     fn insert(&mut self, key: String, value: String) {
         self.map.insert(key, value);
     }
+    fn contains(&self, name: &str) -> bool {
+        self.map.contains_key(name)
+    }
     fn get_by_name(&self, name: &str) -> &String {
         let item = self.map.get(name);
         let result = match item {
@@ -62,7 +85,9 @@ impl Fields {
 enum ControlType {
     PictureBox,
     Label,
-    Button
+    Button,
+    Stack,
+    Grid
 }
 impl Default for ControlType {
     fn default() -> Self { ControlType::PictureBox }
@@ -107,6 +132,12 @@ pub fn instantiate_controls(lines: Vec<String>) -> Controls {
     let mut control = Control { ..Default::default() };
     control.fields.insert("dock_with".to_string(), "".to_string());
     control.fields.insert("offset".to_string(), "0;0".to_string());
+    control.fields.insert("dock_as_child".to_string(), "false".to_string());
+    control.fields.insert("container".to_string(), "".to_string());
+    control.fields.insert("container_index".to_string(), "0".to_string());
+    control.fields.insert("min_size".to_string(), "".to_string());
+    control.fields.insert("max_size".to_string(), "".to_string());
+    control.fields.insert("aspect_ratio".to_string(), "".to_string());
 
     for line in lines {
 
@@ -120,6 +151,8 @@ pub fn instantiate_controls(lines: Vec<String>) -> Controls {
                 "--picture_box--" => { },
                 "--label--" => { },
                 "--button--" => { },
+                "--stack--" => { },
+                "--grid--" => { },
                 "--end--" => { },
                 _ => {
                     panic!("Control type [{}] unknown.", line);
@@ -133,11 +166,16 @@ pub fn instantiate_controls(lines: Vec<String>) -> Controls {
                 global_settings = GlobalSettings { ..Default::default() };
                 global_settings.font_size = "20".to_string();
                 global_settings.color = "255;255;255".to_string();
+                global_settings.label_space = "ui".to_string();
+                global_settings.scale_mode = "none".to_string();
+                global_settings.focus_wrap = "true".to_string();
+                global_settings.camera = "camera_2d".to_string();
             },
             "--picture_box--" => {
                 read_state = ReadState::Control;
                 control.control_type = ControlType::PictureBox;
                 control.fields.insert("draw_order".to_string(), "0.0".to_string());
+                control.fields.insert("blocks_input".to_string(), "false".to_string());
                 control.fields.insert("top_left_position".to_string(), "".to_string());
                 control.fields.insert("center_position".to_string(), "".to_string());
                 control.fields.insert("color".to_string(), global_settings.color.clone());
@@ -152,6 +190,13 @@ pub fn instantiate_controls(lines: Vec<String>) -> Controls {
                 control.fields.insert("font_size".to_string(), global_settings.font_size.clone());
                 control.fields.insert("text_string".to_string(), "".to_string());
                 control.fields.insert("static_text".to_string(), "false".to_string());
+                control.fields.insert("size".to_string(), "".to_string());
+                control.fields.insert("horizontal_align".to_string(), "left".to_string());
+                control.fields.insert("vertical_align".to_string(), "top".to_string());
+                control.fields.insert("wrap".to_string(), "true".to_string());
+                control.fields.insert("auto_size".to_string(), "false".to_string());
+                control.fields.insert("label_space".to_string(), global_settings.label_space.clone());
+                control.fields.insert("draw_order".to_string(), "0.0".to_string());
             },
             "--button--" => {
                 read_state = ReadState::Control;
@@ -160,13 +205,53 @@ pub fn instantiate_controls(lines: Vec<String>) -> Controls {
                 control.fields.insert("texture_name_active".to_string(), "".to_string());
                 control.fields.insert("texture_name_disabled".to_string(), "".to_string());
                 control.fields.insert("on_click_sound".to_string(), "".to_string());
+                control.fields.insert("on_hover_sound".to_string(), "".to_string());
+                control.fields.insert("on_click_button".to_string(), "left".to_string());
+                control.fields.insert("on_click".to_string(), "".to_string());
+                control.fields.insert("enabled".to_string(), "true".to_string());
+                control.fields.insert("cooldown".to_string(), "0.5".to_string());
+                control.fields.insert("active_display_time".to_string(), "0.5".to_string());
+                control.fields.insert("double_click_time".to_string(), "0.5".to_string());
+                control.fields.insert("activate_on".to_string(), "press".to_string());
+                control.fields.insert("repeat_delay".to_string(), "0.5".to_string());
+                control.fields.insert("repeat_interval".to_string(), "0".to_string());
+                control.fields.insert("long_press_time".to_string(), "0".to_string());
                 control.fields.insert("bounding_box".to_string(), "0;0;0;0".to_string());
                 control.fields.insert("bounding_circle".to_string(), "0;0;0".to_string());
+                control.fields.insert("bounding_polygon".to_string(), "".to_string());
+                control.fields.insert("hit_test".to_string(), "shape".to_string());
+                control.fields.insert("alpha_threshold".to_string(), "0.5".to_string());
+                control.fields.insert("tab_index".to_string(), "0".to_string());
+                control.fields.insert("nav_up".to_string(), "".to_string());
+                control.fields.insert("nav_down".to_string(), "".to_string());
+                control.fields.insert("nav_left".to_string(), "".to_string());
+                control.fields.insert("nav_right".to_string(), "".to_string());
+                control.fields.insert("default_focus".to_string(), "false".to_string());
                 control.fields.insert("draw_order".to_string(), "0.0".to_string());
                 control.fields.insert("top_left_position".to_string(), "".to_string());
                 control.fields.insert("center_position".to_string(), "".to_string());
                 control.fields.insert("color".to_string(), global_settings.color.clone());
             }
+            "--stack--" => {
+                read_state = ReadState::Control;
+                control.control_type = ControlType::Stack;
+                control.fields.insert("size".to_string(), "".to_string());
+                control.fields.insert("direction".to_string(), "vertical".to_string());
+                control.fields.insert("spacing".to_string(), "0".to_string());
+                control.fields.insert("padding".to_string(), "0".to_string());
+                control.fields.insert("align".to_string(), "start".to_string());
+                control.fields.insert("top_left_position".to_string(), "".to_string());
+                control.fields.insert("center_position".to_string(), "".to_string());
+            },
+            "--grid--" => {
+                read_state = ReadState::Control;
+                control.control_type = ControlType::Grid;
+                control.fields.insert("size".to_string(), "".to_string());
+                control.fields.insert("spacing".to_string(), "0;0".to_string());
+                control.fields.insert("padding".to_string(), "0".to_string());
+                control.fields.insert("top_left_position".to_string(), "".to_string());
+                control.fields.insert("center_position".to_string(), "".to_string());
+            },
             "--end--" => {
                 match read_state {
                     ReadState::None => { panic!("End found while not in a valid state. Line #{}: {}.", line_number, line); },
@@ -179,6 +264,12 @@ pub fn instantiate_controls(lines: Vec<String>) -> Controls {
                 control = Control { ..Default::default() };
                 control.fields.insert("dock_with".to_string(), "".to_string());
                 control.fields.insert("offset".to_string(), "0;0".to_string());
+                control.fields.insert("dock_as_child".to_string(), "false".to_string());
+                control.fields.insert("container".to_string(), "".to_string());
+                control.fields.insert("container_index".to_string(), "0".to_string());
+                control.fields.insert("min_size".to_string(), "".to_string());
+                control.fields.insert("max_size".to_string(), "".to_string());
+                control.fields.insert("aspect_ratio".to_string(), "".to_string());
             },
             _ => {
                 let split = line.split(':').collect::<Vec<&str>>();
@@ -192,6 +283,19 @@ pub fn instantiate_controls(lines: Vec<String>) -> Controls {
                             "font_name" => { global_settings.font_name = field_value; },
                             "font_size" => { global_settings.font_size = field_value; },
                             "color" => { global_settings.color = field_value; },
+                            "label_space" => { global_settings.label_space = field_value; },
+                            "reference_resolution" => {
+                                // the scale divides by it (empty means no scaling)
+                                let reference_resolution = parse_vec2(&field_value);
+                                if !field_value.is_empty() && (reference_resolution.x <= 0.0 || reference_resolution.y <= 0.0) {
+                                    panic!("reference_resolution [{}] needs a width and height above 0. Line#{}: {}.", field_value, line_number, line);
+                                }
+                                global_settings.reference_resolution = field_value;
+                            },
+                            "scale_mode" => { global_settings.scale_mode = field_value; },
+                            "safe_area" => { global_settings.safe_area = field_value; },
+                            "focus_wrap" => { global_settings.focus_wrap = field_value; },
+                            "camera" => { global_settings.camera = field_value; },
                             _ => { panic!("Unknown field. Line#{}: {}.", line_number, line); }
                         }
                     },
@@ -208,46 +312,209 @@ pub fn instantiate_controls(lines: Vec<String>) -> Controls {
         }
     }
 
-    let result = Controls { map: controls };
+    validate_controls(&controls);
+    assign_container_members(&mut controls);
+
+    let result = Controls { map: controls, global_settings, safe_area_override: None };
 
     result
 }
 
+fn assign_container_members(controls: &mut HashMap<String, Control>) {
+    let mut members = Vec::new();
+    for (container_name, container) in controls.iter() {
+        if !container.is_container() { continue; }
+
+        let member_names = parse_names(container.fields.get_by_name("controls"));
+        for (index, member_name) in member_names.into_iter().enumerate() {
+            members.push((member_name, container_name.clone(), index));
+        }
+    }
+
+    for (member_name, container_name, index) in members {
+        let member = match controls.get_mut(&member_name) {
+            None => panic!("Control [{}] in container [{}] not found.", member_name, container_name),
+            Some(member) => member
+        };
+        if !member.fields.get_by_name("container").is_empty() {
+            panic!("Control [{}] can't be in more than one container.", member_name);
+        }
+        if !member.fields.get_by_name("dock_with").is_empty() {
+            panic!("Control [{}] is in container [{}], so it can't also be docked.", member_name, container_name);
+        }
+
+        member.fields.insert("container".to_string(), container_name);
+        member.fields.insert("container_index".to_string(), index.to_string());
+    }
+}
+
+// catches names that don't lead anywhere while parsing, rather than when the controls are laid out or navigated
+fn validate_controls(controls: &HashMap<String, Control>) {
+    let mut default_focus_count = 0;
+    for (name, control) in controls.iter() {
+        if let Some(dock_target_name) = get_dock_target_name(control) {
+            if !controls.contains_key(&dock_target_name) {
+                panic!("Control [{}] is docked to [{}], which is not found.", name, dock_target_name);
+            }
+        }
+
+        if control.is_container() {
+            let (container_type, required_field_names) = match control.control_type {
+                ControlType::Grid => ("Grid", vec!["controls", "columns", "cell_size"]),
+                _ => ("Stack", vec!["controls"])
+            };
+            for field_name in required_field_names {
+                if !control.fields.contains(field_name) {
+                    panic!("{} [{}] has no {} field.", container_type, name, field_name);
+                }
+            }
+
+            if let ControlType::Grid = control.control_type {
+                let columns = control.fields.get_by_name("columns");
+                match columns.trim().parse::<usize>() {
+                    Ok(columns) if columns > 0 => { },
+                    _ => panic!("Grid [{}] has columns [{}], which is not a whole number above 0.", name, columns)
+                }
+            }
+        }
+
+        if let ControlType::Button = control.control_type {
+            for field_name in ["nav_up", "nav_down", "nav_left", "nav_right"].iter() {
+                let neighbour_name = control.fields.get_by_name(field_name);
+                if neighbour_name.is_empty() { continue; }
+
+                match controls.get(neighbour_name) {
+                    None => panic!("Control [{}] has {} [{}], which is not found.", name, field_name, neighbour_name),
+                    Some(Control { control_type: ControlType::Button, .. }) => { },
+                    Some(_) => panic!("Control [{}] has {} [{}], which is not a button.", name, field_name, neighbour_name)
+                }
+            }
+
+            if parse_bool(control.fields.get_by_name("default_focus")) {
+                default_focus_count += 1;
+            }
+
+            for action in parse_click_actions(control.fields.get_by_name("on_click")) {
+                match action {
+                    ClickAction::Toggle(target_name) => {
+                        if !controls.contains_key(&target_name) {
+                            panic!("Control [{}] toggles [{}], which is not found.", name, target_name);
+                        }
+                    },
+                    // checked here, so a typo doesn't only show up when the button is clicked
+                    ClickAction::OpenScreen(ui_filename) => {
+                        if !Path::new(&format!("assets/{}", ui_filename)).is_file() {
+                            panic!("Control [{}] opens screen [{}], which is not found.", name, ui_filename);
+                        }
+                    },
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    if default_focus_count > 1 {
+        panic!("Only one control can have default_focus.");
+    }
+}
+
+// the group moves to another window (and is laid out for it) once its camera is found there
 pub fn spawn_controls(commands: &mut Commands, asset_server: Res<AssetServer>, mut materials: ResMut<Assets<ColorMaterial>>, controls: Controls, screen_size: Vec2, control_group_name: String) -> Vec<Entity> {
+    let results = spawn_control_group(commands, &asset_server, &mut materials, controls, screen_size, WindowId::primary(), control_group_name);
+
+    results
+}
+
+// also used by the open_screen action, which can't hand its resources over
+pub(crate) fn spawn_control_group(commands: &mut Commands, asset_server: &Res<AssetServer>, materials: &mut ResMut<Assets<ColorMaterial>>, controls: Controls, screen_size: Vec2, window: WindowId, control_group_name: String) -> Vec<Entity> {
     let mut results = Vec::new();
+    let mut entities = HashMap::new();
+    let scale = calculate_scale(&controls, screen_size);
+    let layout_screen_size = screen_size / scale;
     let controls_map = &controls.map;
     for (_, control) in controls_map {
+        if control.is_container() { continue; } // containers only lay out their members
 
-        let size = parse_vec2(control.fields.get_by_name("size"));
-        let top_left_position = calculate_top_left_position(control, &controls, screen_size);
+        let size = calculate_size(control, &controls, layout_screen_size);
+        let top_left_position = calculate_top_left_position(control, &controls, layout_screen_size);
+        let parent_position = get_parent_position(control, &controls, layout_screen_size, scale);
 
-        let entity = spawn_control(control, top_left_position, size, &asset_server, &mut materials, commands, &control_group_name, screen_size);
+        let entity = spawn_control(control, top_left_position, size, scale, parent_position, asset_server, materials, commands, &control_group_name, screen_size);
 
+        entities.insert(control.name.clone(), entity);
         results.push(entity);
     }
 
+    for (_, control) in controls_map {
+        if !parse_bool(control.fields.get_by_name("dock_as_child")) { continue; }
+
+        if let Some(dock_target_name) = get_dock_target_name(control) {
+            let parent = entities[&dock_target_name];
+            let child = entities[&control.name];
+            commands.entity(parent).push_children(&[child]);
+        }
+    }
+
+    let focus_wrap = parse_bool(&controls.global_settings.focus_wrap);
+    for (_, control) in controls_map {
+        if let ControlType::Button = control.control_type {
+            let focus_navigation = FocusNavigation {
+                up: get_focus_neighbour(control, "nav_up", &entities),
+                down: get_focus_neighbour(control, "nav_down", &entities),
+                left: get_focus_neighbour(control, "nav_left", &entities),
+                right: get_focus_neighbour(control, "nav_right", &entities),
+                wrap: focus_wrap
+            };
+            let mut entity_commands = commands.entity(entities[&control.name]);
+            entity_commands.insert(focus_navigation);
+            if parse_bool(control.fields.get_by_name("default_focus")) {
+                entity_commands.insert(DefaultFocus);
+            }
+        }
+    }
+
+    // the group keeps its controls around so that it can be laid out again (e.g. once auto sized labels have been measured)
+    commands
+        .spawn()
+        .insert(GergControlGroup { controls, entities, screen_size, scale, layout_changed: false, window, camera: None })
+        .insert(GergControl { group_name: control_group_name });
+
     results
 }
 
-fn spawn_control(control: &Control, top_left_position: Vec2, size: Vec2, asset_server: &Res<AssetServer>, materials: &mut ResMut<Assets<ColorMaterial>>, commands: &mut Commands, control_group_name: &String, screen_size: Vec2) -> Entity {
+fn get_focus_neighbour(control: &Control, field_name: &str, entities: &HashMap<String, Entity>) -> Option<Entity> {
+    let neighbour_name = control.fields.get_by_name(field_name);
+    let result = if neighbour_name.is_empty() {
+        None
+    } else {
+        Some(entities[neighbour_name])
+    };
+
+    result
+}
+
+fn spawn_control(control: &Control, top_left_position: Vec2, size: Vec2, scale: f32, parent_position: Option<Vec3>, asset_server: &Res<AssetServer>, materials: &mut ResMut<Assets<ColorMaterial>>, commands: &mut Commands, control_group_name: &String, screen_size: Vec2) -> Entity {
     let entity = match control.control_type {
         ControlType::PictureBox => {
-            spawn_picture_box(top_left_position, size, control, asset_server, materials, commands, control_group_name)
+            spawn_picture_box(top_left_position, size, scale, parent_position, control, asset_server, materials, commands, control_group_name)
         },
         ControlType::Label => {
-            spawn_label(top_left_position, screen_size, control, asset_server, size, commands, control_group_name)
+            spawn_label(top_left_position, screen_size, control, asset_server, size, scale, parent_position, commands, control_group_name)
         },
         ControlType::Button => {
-            spawn_button(top_left_position, size, control, asset_server, materials, commands, control_group_name)
+            spawn_button(top_left_position, size, scale, parent_position, control, asset_server, materials, commands, control_group_name)
+        },
+        ControlType::Stack | ControlType::Grid => {
+            panic!("Container [{}] has nothing to spawn.", control.name)
         }
     };
 
     entity
 }
 
-fn spawn_picture_box(top_left_position: Vec2, size: Vec2, control: &Control, asset_server: &Res<AssetServer>, materials: &mut ResMut<Assets<ColorMaterial>>, commands: &mut Commands, control_group_name: &String) -> Entity {
-    let center_position = Vec3::new(top_left_position.x + size.x * 0.5, top_left_position.y - size.y * 0.5, parse_f32(control.fields.get_by_name("draw_order")));
-    let scale = Vec3::new(1.0, 1.0, 1.0);
+fn spawn_picture_box(top_left_position: Vec2, size: Vec2, scale: f32, parent_position: Option<Vec3>, control: &Control, asset_server: &Res<AssetServer>, materials: &mut ResMut<Assets<ColorMaterial>>, commands: &mut Commands, control_group_name: &String) -> Entity {
+    let center_position = get_center_position(top_left_position, size, scale).extend(parse_f32(control.fields.get_by_name("draw_order")));
+    let (center_position, scale) = to_local_transform(center_position, scale, parent_position);
     let texture_name = control.fields.get_by_name("texture_name");
     let color_material_handle = get_color_material_handle(texture_name, asset_server, control, materials);
 
@@ -258,40 +525,78 @@ fn spawn_picture_box(top_left_position: Vec2, size: Vec2, control: &Control, ass
         .insert(GergControl { group_name: control_group_name.clone() })
         .id();
 
+    if parse_bool(control.fields.get_by_name("blocks_input")) {
+        commands.entity(entity).insert(BlocksInput);
+    }
+
     entity
 }
 
-fn spawn_label(top_left_position: Vec2, screen_size: Vec2, control: &Control, asset_server: &Res<AssetServer>, size: Vec2, commands: &mut Commands, control_group_name: &String) -> Entity {
-    let top_left_position = Vec2::new(top_left_position.x + screen_size.x * 0.5, screen_size.y * 0.5 - top_left_position.y);
-    let min_size = Vec2::new(0.0, 0.0);
+fn spawn_label(top_left_position: Vec2, screen_size: Vec2, control: &Control, asset_server: &Res<AssetServer>, size: Vec2, scale: f32, parent_position: Option<Vec3>, commands: &mut Commands, control_group_name: &String) -> Entity {
+    let wrap = parse_bool(control.fields.get_by_name("wrap"));
+    // text is only constrained (and therefore wrapped) when wrapping is on and there is a size to wrap to
+    let wrap_size = if wrap && size.x > 0.0 { Some(size) } else { None };
+    let label_layout = LabelLayout {
+        top_left_position: top_left_position * scale,
+        size: size * scale,
+        screen_size,
+        horizontal_align: parse_horizontal_align(control.fields.get_by_name("horizontal_align")),
+        vertical_align: parse_vertical_align(control.fields.get_by_name("vertical_align")),
+        font_size: parse_f32(control.fields.get_by_name("font_size")),
+        wrap_size,
+        scale,
+        parent_position
+    };
+    let top_left_position = label_layout.top_left_position;
     let text = control.fields.get_by_name("text_string");
     let font_handle: Handle<Font> = asset_server.load(format!("fonts/{}", control.fields.get_by_name("font_name")).as_str());
-    let font_size = parse_f32(control.fields.get_by_name("font_size"));
+    let font_size = get_label_font_size(&label_layout);
     let color = to_bevy_color(parse_color(control.fields.get_by_name("color")));
     let static_text = parse_bool(control.fields.get_by_name("static_text"));
+    let auto_size = parse_bool(control.fields.get_by_name("auto_size"));
+    let alignment = TextAlignment { horizontal: label_layout.horizontal_align, vertical: label_layout.vertical_align };
+    let label_space = control.fields.get_by_name("label_space");
+
+    let mut entity_commands = match label_space.to_lowercase().as_str() {
+        "ui" => {
+            if parent_position.is_some() {
+                panic!("Label [{}] is in ui space, so it can't be docked as a child.", control.name);
+            }
 
-    let bundle = instantiate_textbundle(top_left_position, min_size, size, text, font_handle, font_size, color);
-    let entity = if static_text {
-        commands
-            .spawn_bundle(bundle)
-            .insert(GergLabel { name: control.fields.get_by_name("name").clone() })
-            .insert(GergControl { group_name: control_group_name.clone() })
-            .id()
-    } else {
-        commands
-            .spawn_bundle(bundle)
-            .insert(GergLabel { name: control.fields.get_by_name("name").clone() })
-            .insert(GergControl { group_name: control_group_name.clone() })
-            .insert(TextChanges)
-            .id()
+            let top_left_position = Vec2::new(top_left_position.x + screen_size.x * 0.5, screen_size.y * 0.5 - top_left_position.y);
+            let (min_size, max_size) = get_label_size_constraints(&label_layout);
+
+            let bundle = instantiate_textbundle(top_left_position, min_size, max_size, text, font_handle, font_size, color, alignment);
+            commands.spawn_bundle(bundle)
+        },
+        "world" => {
+            // same space as the sprites, so draw_order is honored (text is not wrapped in this space)
+            let position = Vec3::new(top_left_position.x, top_left_position.y, parse_f32(control.fields.get_by_name("draw_order")));
+            let (position, _) = to_local_transform(position, scale, parent_position);
+
+            let bundle = instantiate_text2d_bundle(position, text, font_handle, font_size, color, alignment);
+            commands.spawn_bundle(bundle)
+        },
+        _ => panic!("Label space [{}] unknown.", label_space)
     };
+    entity_commands
+        .insert(GergLabel { name: control.fields.get_by_name("name").clone() })
+        .insert(GergControl { group_name: control_group_name.clone() })
+        .insert(label_layout);
+    if !static_text {
+        entity_commands.insert(TextChanges);
+    }
+    if auto_size {
+        entity_commands.insert(AutoSize);
+    }
+    let entity = entity_commands.id();
 
     entity
 }
 
-fn spawn_button(top_left_position: Vec2, size: Vec2, control: &Control, asset_server: &Res<AssetServer>, materials: &mut ResMut<Assets<ColorMaterial>>, commands: &mut Commands, control_group_name: &String) -> Entity {
-    let center_position = Vec3::new(top_left_position.x + size.x * 0.5, top_left_position.y - size.y * 0.5, parse_f32(control.fields.get_by_name("draw_order")));
-    let scale = Vec3::new(1.0, 1.0, 1.0);
+fn spawn_button(top_left_position: Vec2, size: Vec2, scale: f32, parent_position: Option<Vec3>, control: &Control, asset_server: &Res<AssetServer>, materials: &mut ResMut<Assets<ColorMaterial>>, commands: &mut Commands, control_group_name: &String) -> Entity {
+    let center_position = get_center_position(top_left_position, size, scale).extend(parse_f32(control.fields.get_by_name("draw_order")));
+    let (center_position, scale) = to_local_transform(center_position, scale, parent_position);
     let bounding_box = parse_vec4(control.fields.get_by_name("bounding_box"));
     let bounding_circle = parse_vec3(control.fields.get_by_name("bounding_circle"));
     let texture_name_normal = control.fields.get_by_name("texture_name_normal");
@@ -312,20 +617,41 @@ fn spawn_button(top_left_position: Vec2, size: Vec2, control: &Control, asset_se
     let color_material_handle_active = get_color_material_handle(texture_name_active, asset_server, control, materials);
     let color_material_handle_disabled = get_color_material_handle(texture_name_disabled, asset_server, control, materials);
     let on_click_sound = control.fields.get_by_name("on_click_sound");
+    let (button_state, color_material_handle) = if parse_bool(control.fields.get_by_name("enabled")) {
+        (ButtonState::Normal, color_material_handle_normal.clone())
+    } else {
+        (ButtonState::Disabled, color_material_handle_disabled.clone())
+    };
 
-    let bundle = instantiate_sprite_bundle(size, center_position, scale, color_material_handle_normal.clone(), true);
+    let bundle = instantiate_sprite_bundle(size, center_position, scale, color_material_handle, true);
     let entity = commands
         .spawn_bundle(bundle)
         .insert(GergButton {
             name: control.fields.get_by_name("name").clone(),
-            button_state: ButtonState::Normal,
+            button_state,
             color_material_handle_normal: color_material_handle_normal,
             color_material_handle_hover: color_material_handle_hover,
             color_material_handle_active: color_material_handle_active,
             color_material_handle_disabled: color_material_handle_disabled,
             on_click_sound: on_click_sound.to_string(),
+            on_hover_sound: control.fields.get_by_name("on_hover_sound").clone(),
+            hovered: false,
             bounding_box,
-            bounding_circle
+            bounding_circle,
+            bounding_polygon: parse_polygon(control.fields.get_by_name("bounding_polygon")),
+            hit_test: parse_hit_test(control.fields.get_by_name("hit_test")),
+            alpha_threshold: parse_f32(control.fields.get_by_name("alpha_threshold")),
+            tab_index: control.fields.get_by_name("tab_index").trim().parse::<i32>().unwrap(),
+            on_click_button: parse_click_button(control.fields.get_by_name("on_click_button")),
+            double_click_time: parse_f32(control.fields.get_by_name("double_click_time")),
+            last_click: None,
+            activate_on: parse_activate_on(control.fields.get_by_name("activate_on")),
+            repeat_delay: parse_f32(control.fields.get_by_name("repeat_delay")),
+            repeat_interval: parse_f32(control.fields.get_by_name("repeat_interval")),
+            long_press_time: parse_f32(control.fields.get_by_name("long_press_time")),
+            on_click: parse_click_actions(control.fields.get_by_name("on_click")),
+            cooldown: parse_f32(control.fields.get_by_name("cooldown")),
+            active_display_time: parse_f32(control.fields.get_by_name("active_display_time"))
         })
         .insert(GergControl { group_name: control_group_name.clone() })
         .id();
@@ -333,6 +659,80 @@ fn spawn_button(top_left_position: Vec2, size: Vec2, control: &Control, asset_se
     entity
 }
 
+fn get_label_size_constraints(label_layout: &LabelLayout) -> (Size<Val>, Size<Val>) {
+    let result = match label_layout.wrap_size {
+        Some(wrap_size) => {
+            let wrap_size = wrap_size * label_layout.scale;
+
+            (Size::new(Val::Px(0.0), Val::Px(0.0)), Size::new(Val::Px(wrap_size.x), Val::Px(wrap_size.y)))
+        },
+        None => (Size::new(Val::Undefined, Val::Undefined), Size::new(Val::Undefined, Val::Undefined))
+    };
+
+    result
+}
+
+fn get_center_position(top_left_position: Vec2, size: Vec2, scale: f32) -> Vec2 {
+    let center_position = Vec2::new(top_left_position.x + size.x * 0.5, top_left_position.y - size.y * 0.5) * scale;
+
+    center_position
+}
+
+// children inherit their parent's scale, so only controls without a parent get scaled
+fn to_local_transform(position: Vec3, scale: f32, parent_position: Option<Vec3>) -> (Vec3, Vec3) {
+    let result = match parent_position {
+        Some(parent_position) => {
+            let local_position = position - parent_position;
+
+            (Vec3::new(local_position.x / scale, local_position.y / scale, local_position.z), Vec3::new(1.0, 1.0, 1.0))
+        },
+        None => (position, Vec3::new(scale, scale, 1.0))
+    };
+
+    result
+}
+
+// the position (and draw order) of the dock target, if the control is to be spawned as its child
+fn get_parent_position(control: &Control, controls: &Controls, screen_size: Vec2, scale: f32) -> Option<Vec3> {
+    if !parse_bool(control.fields.get_by_name("dock_as_child")) {
+        return None;
+    }
+
+    let dock_target_name = get_dock_target_name(control)?;
+    let parent = controls.get_by_name(dock_target_name);
+    match parent.control_type {
+        ControlType::PictureBox | ControlType::Button => { },
+        _ => panic!("Control [{}] can only be docked as a child of a picture box or a button, not [{}].", control.name, parent.name)
+    }
+    let parent_size = calculate_size(parent, controls, screen_size);
+    let parent_top_left_position = calculate_top_left_position(parent, controls, screen_size);
+    let parent_center_position = get_center_position(parent_top_left_position, parent_size, scale);
+
+    Some(parent_center_position.extend(parse_f32(parent.fields.get_by_name("draw_order"))))
+}
+
+fn calculate_scale(controls: &Controls, screen_size: Vec2) -> f32 {
+    let reference_resolution = &controls.global_settings.reference_resolution;
+    if reference_resolution.is_empty() {
+        return 1.0;
+    }
+
+    let reference_resolution = parse_vec2(reference_resolution);
+    let scale_x = screen_size.x / reference_resolution.x;
+    let scale_y = screen_size.y / reference_resolution.y;
+    let scale_mode = &controls.global_settings.scale_mode;
+    let scale = match scale_mode.to_lowercase().as_str() {
+        "none" => 1.0,
+        "fit" => scale_x.min(scale_y),
+        "fill" => scale_x.max(scale_y),
+        "match_width" => scale_x,
+        "match_height" => scale_y,
+        _ => panic!("Scale mode [{}] unknown.", scale_mode)
+    };
+
+    scale
+}
+
 fn get_color_material_handle(path: &str, asset_server: &Res<AssetServer>, control: &Control, materials: &mut ResMut<Assets<ColorMaterial>>) -> Handle<ColorMaterial> {
     let mut color_material: ColorMaterial = asset_server.load(path).into();
     let color = control.fields.get_by_name("color");
@@ -357,11 +757,145 @@ fn to_bevy_color(color_u32: u32) -> Color {
     color
 }
 
+fn calculate_size(control: &Control, controls: &Controls, screen_size: Vec2) -> Vec2 {
+    let size = control.fields.get_by_name("size");
+    let relative_to_size = get_relative_to_size(control, controls, screen_size);
+    let size = if size.is_empty() && control.is_container() {
+        calculate_container_content_size(control, controls, screen_size)
+    } else {
+        resolve_vec2(size, relative_to_size)
+    };
+    let result = apply_size_constraints(control, size, relative_to_size);
+
+    result
+}
+
+// min_size and max_size are applied first, then the aspect ratio shrinks the size to fit inside that
+fn apply_size_constraints(control: &Control, size: Vec2, relative_to_size: Vec2) -> Vec2 {
+    let mut result = size;
+
+    let min_size = control.fields.get_by_name("min_size");
+    if !min_size.is_empty() {
+        result = result.max(resolve_vec2(min_size, relative_to_size));
+    }
+
+    let max_size = control.fields.get_by_name("max_size");
+    if !max_size.is_empty() {
+        result = result.min(resolve_vec2(max_size, relative_to_size));
+    }
+
+    let aspect_ratio = control.fields.get_by_name("aspect_ratio");
+    if !aspect_ratio.is_empty() && result.y > 0.0 {
+        let aspect_ratio = parse_aspect_ratio(aspect_ratio);
+        if result.x / result.y > aspect_ratio {
+            result.x = result.y * aspect_ratio;
+        } else {
+            result.y = result.x / aspect_ratio;
+        }
+    }
+
+    result
+}
+
+// a container without a size is just big enough for its members (and its padding)
+fn calculate_container_content_size(container: &Control, controls: &Controls, screen_size: Vec2) -> Vec2 {
+    let member_sizes = get_member_sizes(container, controls, screen_size);
+    let padding = parse_f32(container.fields.get_by_name("padding"));
+    let content_size = match container.control_type {
+        ControlType::Stack => {
+            let spacing = parse_f32(container.fields.get_by_name("spacing"));
+            let total_spacing = spacing * member_sizes.len().saturating_sub(1) as f32;
+            let largest = member_sizes.iter().fold(Vec2::ZERO, |largest, size| largest.max(*size));
+            let total = member_sizes.iter().fold(Vec2::ZERO, |total, size| total + *size);
+            let direction = container.fields.get_by_name("direction");
+
+            match direction.to_lowercase().as_str() {
+                "vertical" => Vec2::new(largest.x, total.y + total_spacing),
+                "horizontal" => Vec2::new(total.x + total_spacing, largest.y),
+                _ => panic!("Direction [{}] unknown.", direction)
+            }
+        },
+        ControlType::Grid => {
+            let columns = parse_usize(container.fields.get_by_name("columns"));
+            let cell_size = parse_vec2(container.fields.get_by_name("cell_size"));
+            let spacing = parse_vec2(container.fields.get_by_name("spacing"));
+            let used_columns = member_sizes.len().min(columns);
+            let rows = (member_sizes.len() + columns - 1) / columns;
+
+            Vec2::new(
+                cell_size.x * used_columns as f32 + spacing.x * used_columns.saturating_sub(1) as f32,
+                cell_size.y * rows as f32 + spacing.y * rows.saturating_sub(1) as f32
+            )
+        },
+        _ => panic!("Control [{}] is not a container.", container.name)
+    };
+
+    content_size + Vec2::new(padding * 2.0, padding * 2.0)
+}
+
+fn get_member_sizes(container: &Control, controls: &Controls, screen_size: Vec2) -> Vec<Vec2> {
+    let member_names = parse_names(container.fields.get_by_name("controls"));
+    let member_sizes = member_names
+        .into_iter()
+        .map(|member_name| calculate_size(controls.get_by_name(member_name), controls, screen_size))
+        .collect::<Vec<Vec2>>();
+
+    member_sizes
+}
+
+// relative sizes and offsets (50%, fill, 100%-20) are relative to whatever the control is docked to
+fn get_relative_to_size(control: &Control, controls: &Controls, screen_size: Vec2) -> Vec2 {
+    // members are relative to their container, unless the container's size comes from its members
+    let container_name = control.fields.get_by_name("container");
+    if !container_name.is_empty() {
+        let container = controls.get_by_name(container_name.clone());
+        let result = if container.fields.get_by_name("size").is_empty() {
+            calculate_safe_area_size(controls, screen_size)
+        } else {
+            calculate_size(container, controls, screen_size)
+        };
+
+        return result;
+    }
+
+    let result = match get_dock_target_name(control) {
+        Some(dock_target_name) => {
+            let control_to_dock_to = controls.get_by_name(dock_target_name);
+
+            calculate_size(control_to_dock_to, controls, screen_size)
+        },
+        None => calculate_safe_area_size(controls, screen_size)
+    };
+
+    result
+}
+
+// the name of the control this one is docked to, None if it isn't docked or is docked to the screen
+fn get_dock_target_name(control: &Control) -> Option<String> {
+    let control_dock_with = control.fields.get_by_name("dock_with");
+    if control_dock_with.is_empty() {
+        return None;
+    }
+
+    let split = control_dock_with.split("<->").collect::<Vec<&str>>();
+    let split = split[0].split(".").collect::<Vec<&str>>();
+    let control_to_use_for_docking = split[0];
+    let result = if control_to_use_for_docking.to_lowercase() == "screen" {
+        None
+    } else {
+        Some(control_to_use_for_docking.to_string())
+    };
+
+    result
+}
+
 fn calculate_top_left_position(control: &Control, controls: &Controls, screen_size: Vec2) -> Vec2 {
-    let control_size = parse_vec2(control.fields.get_by_name("size"));
+    let control_size = calculate_size(control, controls, screen_size);
     
     let control_dock_with = control.fields.get_by_name("dock_with");
-    let tlp = if control_dock_with.is_empty() {
+    let tlp = if !control.fields.get_by_name("container").is_empty() {
+        calculate_top_left_position_in_container(control, controls, screen_size, control_size)
+    } else if control_dock_with.is_empty() {
         calculate_top_left_position_without_docking(control, control_size)
     } else {
         calculate_top_left_position_with_docking(control_dock_with, controls, screen_size, control_size, control)
@@ -370,6 +904,51 @@ fn calculate_top_left_position(control: &Control, controls: &Controls, screen_si
     tlp
 }
 
+fn calculate_top_left_position_in_container(control: &Control, controls: &Controls, screen_size: Vec2, control_size: Vec2) -> Vec2 {
+    let container = controls.get_by_name(control.fields.get_by_name("container").clone());
+    let index = parse_usize(control.fields.get_by_name("container_index"));
+    let container_top_left_position = calculate_top_left_position(container, controls, screen_size);
+    let padding = parse_f32(container.fields.get_by_name("padding"));
+    let content_top_left_position = Vec2::new(container_top_left_position.x + padding, container_top_left_position.y - padding);
+
+    let result = match container.control_type {
+        ControlType::Stack => {
+            let member_sizes = get_member_sizes(container, controls, screen_size);
+            let container_size = calculate_size(container, controls, screen_size);
+            let content_size = container_size - Vec2::new(padding * 2.0, padding * 2.0);
+            let spacing = parse_f32(container.fields.get_by_name("spacing"));
+            let align = parse_align(container.fields.get_by_name("align"));
+            let direction = container.fields.get_by_name("direction");
+
+            match direction.to_lowercase().as_str() {
+                "vertical" => {
+                    let before: f32 = member_sizes[..index].iter().map(|size| size.y + spacing).sum();
+                    Vec2::new(content_top_left_position.x + (content_size.x - control_size.x) * align, content_top_left_position.y - before)
+                },
+                "horizontal" => {
+                    let before: f32 = member_sizes[..index].iter().map(|size| size.x + spacing).sum();
+                    Vec2::new(content_top_left_position.x + before, content_top_left_position.y - (content_size.y - control_size.y) * align)
+                },
+                _ => panic!("Direction [{}] unknown.", direction)
+            }
+        },
+        ControlType::Grid => {
+            // members are centered in their cells
+            let columns = parse_usize(container.fields.get_by_name("columns"));
+            let cell_size = parse_vec2(container.fields.get_by_name("cell_size"));
+            let spacing = parse_vec2(container.fields.get_by_name("spacing"));
+            let column = (index % columns) as f32;
+            let row = (index / columns) as f32;
+            let cell_top_left_position = Vec2::new(content_top_left_position.x + column * (cell_size.x + spacing.x), content_top_left_position.y - row * (cell_size.y + spacing.y));
+
+            Vec2::new(cell_top_left_position.x + (cell_size.x - control_size.x) * 0.5, cell_top_left_position.y - (cell_size.y - control_size.y) * 0.5)
+        },
+        _ => panic!("Control [{}] is not a container.", container.name)
+    };
+
+    result
+}
+
 fn calculate_top_left_position_without_docking(control: &Control, control_size: Vec2) -> Vec2 {
     let top_left_position = control.fields.get_by_name("top_left_position");
     let result = if top_left_position.is_empty() {
@@ -416,25 +995,34 @@ fn calculate_top_left_position_with_docking(control_dock_with: &String, controls
         _ => panic!("{} is not implemented.", point_on_this_control_to_anchor_to)
     };
     let offset = control.fields.get_by_name("offset");
-    let result_pixel = pixel2 + parse_vec2(offset);
+    let relative_to_size = get_relative_to_size(control, controls, screen_size);
+    let result_pixel = pixel2 + resolve_vec2(offset, relative_to_size);
 
     result_pixel
 }
 
 fn get_point_to_dock_to(controls: &Controls, control_to_use_for_docking: &str, point_on_control_to_anchor_to: &str, screen_size: Vec2) -> Vec2 {
     let result = if control_to_use_for_docking.to_lowercase() == "screen" {
+        // the screen's anchors are on the edges of its safe area
+        let safe_area = calculate_safe_area(controls, screen_size);
+        let left = -screen_size.x * 0.5 + safe_area.left;
+        let right = screen_size.x * 0.5 - safe_area.right;
+        let top = screen_size.y * 0.5 - safe_area.top;
+        let bottom = -screen_size.y * 0.5 + safe_area.bottom;
+        let middle = (left + right) * 0.5;
+        let center = (top + bottom) * 0.5;
         let point = match point_on_control_to_anchor_to.to_lowercase().as_str() {
-            "top_left" => Vec2::new(-screen_size.x * 0.5, screen_size.y * 0.5),
-            "center_left" => Vec2::new(-screen_size.x * 0.5, 0.0),
-            "bottom_left" => Vec2::new(-screen_size.x * 0.5, -screen_size.y * 0.5),
+            "top_left" => Vec2::new(left, top),
+            "center_left" => Vec2::new(left, center),
+            "bottom_left" => Vec2::new(left, bottom),
         
-            "top_middle" => Vec2::new(0.0, screen_size.y * 0.5),
-            "center_middle" => Vec2::new(0.0, 0.0),
-            "bottom_middle" => Vec2::new(0.0, -screen_size.y * 0.5),
+            "top_middle" => Vec2::new(middle, top),
+            "center_middle" => Vec2::new(middle, center),
+            "bottom_middle" => Vec2::new(middle, bottom),
     
-            "top_right" => Vec2::new(screen_size.x * 0.5, screen_size.y * 0.5),
-            "center_right" => Vec2::new(screen_size.x * 0.5, 0.0),
-            "bottom_right" => Vec2::new(screen_size.x * 0.5, -screen_size.y * 0.5),
+            "top_right" => Vec2::new(right, top),
+            "center_right" => Vec2::new(right, center),
+            "bottom_right" => Vec2::new(right, bottom),
     
             _ => panic!("{} is not implemented.", point_on_control_to_anchor_to)
         };
@@ -442,7 +1030,7 @@ fn get_point_to_dock_to(controls: &Controls, control_to_use_for_docking: &str, p
         point
     } else {
         let control_to_dock_to = controls.get_by_name(control_to_use_for_docking.to_string());
-        let control_to_dock_to_size = parse_vec2(control_to_dock_to.fields.get_by_name("size"));
+        let control_to_dock_to_size = calculate_size(control_to_dock_to, controls, screen_size);
         let parent_top_left_position = calculate_top_left_position(control_to_dock_to, controls, screen_size);
         let point = match point_on_control_to_anchor_to.to_lowercase().as_str() {
             "top_left" => Vec2::new(parent_top_left_position.x, parent_top_left_position.y),
@@ -466,6 +1054,39 @@ fn get_point_to_dock_to(controls: &Controls, control_to_use_for_docking: &str, p
     result
 }
 
+// margins in from the screen's edges, set in global_settings or overridden at runtime through the SafeArea resource
+fn calculate_safe_area(controls: &Controls, screen_size: Vec2) -> Rect<f32> {
+    let safe_area = match controls.safe_area_override {
+        Some(safe_area) => safe_area,
+        None => parse_safe_area(&controls.global_settings.safe_area)
+    };
+    let result = Rect {
+        left: resolve_val(safe_area.left, screen_size.x),
+        right: resolve_val(safe_area.right, screen_size.x),
+        top: resolve_val(safe_area.top, screen_size.y),
+        bottom: resolve_val(safe_area.bottom, screen_size.y)
+    };
+
+    result
+}
+
+fn calculate_safe_area_size(controls: &Controls, screen_size: Vec2) -> Vec2 {
+    let safe_area = calculate_safe_area(controls, screen_size);
+    let result = Vec2::new(screen_size.x - safe_area.left - safe_area.right, screen_size.y - safe_area.top - safe_area.bottom);
+
+    result
+}
+
+fn resolve_val(val: Val, relative_to: f32) -> f32 {
+    let result = match val {
+        Val::Px(value) => value,
+        Val::Percent(percentage) => relative_to * percentage / 100.0,
+        _ => 0.0
+    };
+
+    result
+}
+
 fn get_string(s: String) -> String {
     let right_side_of_colon = get_right_side_of_colon(s);
     let result = right_side_of_colon.to_string();
@@ -479,6 +1100,45 @@ fn parse_f32(s: &String) -> f32 {
     result
 }
 
+fn parse_usize(s: &String) -> usize {
+    let result = s.trim().parse::<usize>().unwrap();
+
+    result
+}
+
+fn parse_names(s: &String) -> Vec<String> {
+    let result = s
+        .split(';')
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect::<Vec<String>>();
+
+    result
+}
+
+fn parse_align(s: &String) -> f32 {
+    let result = match s.trim().to_lowercase().as_str() {
+        "start" => 0.0,
+        "center" => 0.5,
+        "end" => 1.0,
+        _ => panic!("{} is not implemented.", s)
+    };
+
+    result
+}
+
+// either width:height (16:9) or a plain ratio (1.777)
+fn parse_aspect_ratio(s: &String) -> f32 {
+    let split = s.split(':').collect::<Vec<&str>>();
+    let result = if split.len() == 2 {
+        split[0].trim().parse::<f32>().unwrap() / split[1].trim().parse::<f32>().unwrap()
+    } else {
+        s.trim().parse::<f32>().unwrap()
+    };
+
+    result
+}
+
 fn parse_vec2(s: &String) -> Vec2 {
     let result = if s.is_empty() {
         Vec2::new(0.0, 0.0)
@@ -494,6 +1154,92 @@ fn parse_vec2(s: &String) -> Vec2 {
     result
 }
 
+fn resolve_vec2(s: &String, relative_to: Vec2) -> Vec2 {
+    let result = if s.is_empty() {
+        Vec2::new(0.0, 0.0)
+    } else if s.trim().to_lowercase() == "fill" {
+        relative_to
+    } else {
+        let split = s.split(';').collect::<Vec<&str>>();
+        let value1 = resolve_f32(split[0], relative_to.x);
+        let value2 = resolve_f32(split[1], relative_to.y);
+        let result = Vec2::new(value1, value2);
+
+        result
+    };
+
+    result
+}
+
+// resolves a sum of terms such as 100%-20, where a term is either pixels, a percentage or fill
+fn resolve_f32(s: &str, relative_to: f32) -> f32 {
+    let mut result = 0.0;
+    let mut sign = 1.0;
+    let mut term = String::new();
+    for c in s.trim().chars() {
+        if c == '+' || c == '-' {
+            if term.trim().is_empty() {
+                // a sign in front of the first term
+                if c == '-' { sign = -sign; }
+            } else {
+                result += sign * resolve_term(&term, relative_to);
+                term.clear();
+                sign = if c == '-' { -1.0 } else { 1.0 };
+            }
+        } else {
+            term.push(c);
+        }
+    }
+    result += sign * resolve_term(&term, relative_to);
+
+    result
+}
+
+fn resolve_term(s: &str, relative_to: f32) -> f32 {
+    let s = s.trim().to_lowercase();
+    let result = if s == "fill" {
+        relative_to
+    } else if s.ends_with('%') {
+        let percentage = s.trim_end_matches('%').trim().parse::<f32>().expect(format!("[{}] is not a valid percentage.", s).as_str());
+
+        relative_to * percentage / 100.0
+    } else {
+        s.parse::<f32>().expect(format!("[{}] is not a valid value.", s).as_str())
+    };
+
+    result
+}
+
+// one value for all sides, two for top/bottom;left/right or four for top;right;bottom;left
+fn parse_safe_area(s: &String) -> Rect<Val> {
+    let values = s
+        .split(';')
+        .map(|value| value.trim())
+        .filter(|value| !value.is_empty())
+        .map(|value| parse_val(value))
+        .collect::<Vec<Val>>();
+
+    let result = match values.len() {
+        0 => Rect::all(Val::Px(0.0)),
+        1 => Rect::all(values[0]),
+        2 => Rect { top: values[0], bottom: values[0], left: values[1], right: values[1] },
+        4 => Rect { top: values[0], right: values[1], bottom: values[2], left: values[3] },
+        _ => panic!("[{}] is not a valid safe area.", s)
+    };
+
+    result
+}
+
+fn parse_val(s: &str) -> Val {
+    let result = if s.ends_with('%') {
+        Val::Percent(s.trim_end_matches('%').trim().parse::<f32>().expect(format!("[{}] is not a valid percentage.", s).as_str()))
+    } else {
+        Val::Px(s.parse::<f32>().expect(format!("[{}] is not a valid value.", s).as_str()))
+    };
+
+    result
+}
+
 fn parse_vec3(s: &String) -> Vec3 {
     let result = if s.is_empty() {
         Vec3::new(0.0, 0.0, 0.0)
@@ -527,6 +1273,97 @@ fn parse_vec4(s: &String) -> Vec4 {
     result
 }
 
+fn parse_horizontal_align(s: &String) -> HorizontalAlign {
+    let result = match s.trim().to_lowercase().as_str() {
+        "left" => HorizontalAlign::Left,
+        "center" => HorizontalAlign::Center,
+        "right" => HorizontalAlign::Right,
+        _ => panic!("{} is not implemented.", s)
+    };
+
+    result
+}
+
+// None means any mouse button
+fn parse_click_button(s: &String) -> Option<MouseButton> {
+    let result = match s.trim().to_lowercase().as_str() {
+        "left" => Some(MouseButton::Left),
+        "right" => Some(MouseButton::Right),
+        "middle" => Some(MouseButton::Middle),
+        "any" => None,
+        _ => panic!("{} is not implemented.", s)
+    };
+
+    result
+}
+
+// e.g. close_group(screen1.ui);open_screen(options.ui)
+fn parse_click_actions(s: &String) -> Vec<ClickAction> {
+    let mut result = Vec::new();
+    for action in s.split(';').map(|action| action.trim()).filter(|action| !action.is_empty()) {
+        let open = action.find('(').expect(&format!("{} is missing its '('.", action));
+        if !action.ends_with(')') { panic!("{} is missing its ')'.", action); }
+
+        let action_name = action[..open].trim();
+        let argument = action[open + 1..action.len() - 1].trim().to_string();
+        let click_action = match action_name {
+            "close_group" => ClickAction::CloseGroup(argument),
+            "open_screen" => ClickAction::OpenScreen(argument),
+            "emit" => ClickAction::Emit(argument),
+            "toggle" => ClickAction::Toggle(argument),
+            "call" => ClickAction::Call(argument),
+            _ => panic!("{} is not implemented.", action_name)
+        };
+        result.push(click_action);
+    }
+
+    result
+}
+
+fn parse_hit_test(s: &String) -> HitTest {
+    let result = match s.trim().to_lowercase().as_str() {
+        "shape" => HitTest::Shape,
+        "alpha" => HitTest::Alpha,
+        _ => panic!("{} is not implemented.", s)
+    };
+
+    result
+}
+
+// x1;y1;x2;y2;... relative to the center of the control, empty for no polygon
+fn parse_polygon(s: &String) -> Vec<Vec2> {
+    if s.trim().is_empty() { return Vec::new(); }
+
+    let values = s.split(';').map(|value| value.trim().parse::<f32>().expect(&format!("{} is not a valid polygon.", s))).collect::<Vec<f32>>();
+    if values.len() % 2 != 0 || values.len() < 6 {
+        panic!("{} is not a valid polygon, it needs at least three x;y points.", s);
+    }
+    let result = values.chunks(2).map(|point| Vec2::new(point[0], point[1])).collect();
+
+    result
+}
+
+fn parse_activate_on(s: &String) -> ActivateOn {
+    let result = match s.trim().to_lowercase().as_str() {
+        "press" => ActivateOn::Press,
+        "release" => ActivateOn::Release,
+        _ => panic!("{} is not implemented.", s)
+    };
+
+    result
+}
+
+fn parse_vertical_align(s: &String) -> VerticalAlign {
+    let result = match s.trim().to_lowercase().as_str() {
+        "top" => VerticalAlign::Top,
+        "center" => VerticalAlign::Center,
+        "bottom" => VerticalAlign::Bottom,
+        _ => panic!("{} is not implemented.", s)
+    };
+
+    result
+}
+
 fn parse_bool(s: &String) -> bool {
     let result = if s.trim().to_lowercase() == "true" {
         true
@@ -539,7 +1376,7 @@ fn parse_bool(s: &String) -> bool {
 
 fn get_right_side_of_colon(s: String) -> String {
     let split = s.split("//").collect::<Vec<&str>>();
-    let split = split[0].split(':').collect::<Vec<&str>>();
+    let split = split[0].splitn(2, ':').collect::<Vec<&str>>();
     let right_side_of_colon = split[1].trim().to_string();
 
     right_side_of_colon
@@ -573,12 +1410,13 @@ fn instantiate_sprite_bundle(
 
 fn instantiate_textbundle(
     top_left_position: Vec2,
-    min_size: Vec2,
-    max_size: Vec2,
+    min_size: Size<Val>,
+    max_size: Size<Val>,
     text: &String,
     font_handle: Handle<Font>,
     font_size: f32,
-    color: Color
+    color: Color,
+    alignment: TextAlignment
 ) -> TextBundle {
     let position_type = PositionType::Absolute;
     let position = Rect {
@@ -589,23 +1427,14 @@ fn instantiate_textbundle(
     let style = Style {
         position_type,
         position,
-        min_size: Size {
-            width: Val::Px(min_size.x),
-            height: Val::Px(min_size.y)
-        },
-        max_size: Size {
-            width: Val::Px(max_size.x),
-            height: Val::Px(max_size.y)
-        },
+        min_size,
+        max_size,
         ..Default::default()
     };
     let text = Text::with_section(
         text,
         TextStyle { font: font_handle.clone(), font_size: font_size, color: color },
-        TextAlignment {
-            horizontal: HorizontalAlign::Left,
-            vertical: VerticalAlign::Top,
-        },
+        alignment,
     );
     let bundle = TextBundle {
         style,
@@ -615,3 +1444,277 @@ fn instantiate_textbundle(
 
     bundle
 }
+
+fn instantiate_text2d_bundle(
+    position: Vec3,
+    text: &String,
+    font_handle: Handle<Font>,
+    font_size: f32,
+    color: Color,
+    alignment: TextAlignment
+) -> Text2dBundle {
+    let transform = Transform {
+        translation: position,
+        ..Default::default()
+    };
+    let text = Text::with_section(
+        text,
+        TextStyle { font: font_handle.clone(), font_size: font_size, color: color },
+        alignment,
+    );
+    let bundle = Text2dBundle {
+        transform,
+        text,
+        ..Default::default()
+    };
+
+    bundle
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn instantiate_test_controls(lines: &[&str]) -> Controls {
+        let lines = lines.iter().map(|line| line.to_string()).collect::<Vec<String>>();
+        let controls = instantiate_controls(lines);
+
+        controls
+    }
+
+    #[test]
+    fn calculate_scale_follows_the_scale_mode() {
+        // a 960x1080 screen is half the reference width and the full reference height
+        let cases = [("none", 1.0), ("fit", 0.5), ("fill", 1.0), ("match_width", 0.5), ("match_height", 1.0)];
+        for (scale_mode, expected) in cases.iter() {
+            let controls = instantiate_test_controls(&["--global_settings--", "reference_resolution: 1920;1080", format!("scale_mode: {}", scale_mode).as_str(), "--end--"]);
+
+            assert_eq!(calculate_scale(&controls, Vec2::new(960.0, 1080.0)), *expected, "scale_mode: {}", scale_mode);
+        }
+    }
+
+    #[test]
+    fn calculate_scale_is_1_without_a_reference_resolution() {
+        let controls = instantiate_test_controls(&["--global_settings--", "scale_mode: fit", "--end--"]);
+
+        assert_eq!(calculate_scale(&controls, Vec2::new(960.0, 1080.0)), 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "needs a width and height above 0")]
+    fn reference_resolution_without_a_height_is_rejected() {
+        instantiate_test_controls(&["--global_settings--", "reference_resolution: 1920;0", "--end--"]);
+    }
+
+    #[test]
+    fn resolve_f32_sums_pixels_percentages_and_fill() {
+        let cases = [("100%-20", 180.0), ("-20", -20.0), ("fill", 200.0), ("50%+10", 110.0), ("fill-25%", 150.0), ("20", 20.0)];
+        for (s, expected) in cases.iter() {
+            assert_eq!(resolve_f32(s, 200.0), *expected, "{}", s);
+        }
+    }
+
+    #[test]
+    fn resolve_vec2_resolves_each_axis_against_its_own_size() {
+        let relative_to = Vec2::new(800.0, 600.0);
+        let cases = [("50%;fill", Vec2::new(400.0, 600.0)), ("fill", Vec2::new(800.0, 600.0)), ("100%-20;10", Vec2::new(780.0, 10.0)), ("", Vec2::new(0.0, 0.0))];
+        for (s, expected) in cases.iter() {
+            assert_eq!(resolve_vec2(&s.to_string(), relative_to), *expected, "{}", s);
+        }
+    }
+
+    #[test]
+    fn to_local_transform_scales_controls_without_a_parent() {
+        let (translation, scale) = to_local_transform(Vec3::new(100.0, 50.0, 2.0), 2.0, None);
+
+        assert_eq!(translation, Vec3::new(100.0, 50.0, 2.0));
+        assert_eq!(scale, Vec3::new(2.0, 2.0, 1.0));
+    }
+
+    #[test]
+    fn to_local_transform_makes_children_relative_to_their_unscaled_parent() {
+        // children inherit the parent's transform, so their offset (and draw order) is relative to it, in its unscaled space
+        let cases = [(1.0, Vec3::new(40.0, 40.0, 1.0)), (2.0, Vec3::new(20.0, 20.0, 1.0)), (0.5, Vec3::new(80.0, 80.0, 1.0))];
+        for (scale, expected) in cases.iter() {
+            let (translation, local_scale) = to_local_transform(Vec3::new(100.0, 50.0, 2.0), *scale, Some(Vec3::new(60.0, 10.0, 1.0)));
+
+            assert_eq!(translation, *expected, "scale: {}", scale);
+            assert_eq!(local_scale, Vec3::new(1.0, 1.0, 1.0));
+        }
+    }
+
+    #[test]
+    fn get_label_font_size_only_scales_labels_without_a_parent() {
+        // a docked child already inherits its parent's scale, so scaling its font too would draw it at scale squared
+        let cases = [(None, 40.0), (Some(Vec3::new(60.0, 10.0, 1.0)), 20.0)];
+        for (parent_position, expected) in cases.iter() {
+            let label_layout = LabelLayout {
+                top_left_position: Vec2::new(0.0, 0.0),
+                size: Vec2::new(0.0, 0.0),
+                screen_size: Vec2::new(1920.0, 1080.0),
+                horizontal_align: HorizontalAlign::Left,
+                vertical_align: VerticalAlign::Top,
+                font_size: 20.0,
+                wrap_size: None,
+                scale: 2.0,
+                parent_position: *parent_position
+            };
+
+            assert_eq!(get_label_font_size(&label_layout), *expected, "parent_position: {:?}", parent_position);
+        }
+    }
+
+    #[test]
+    fn calculate_container_content_size_of_a_stack_follows_its_direction() {
+        let cases = [("vertical", Vec2::new(110.0, 80.0)), ("horizontal", Vec2::new(180.0, 50.0))];
+        for (direction, expected) in cases.iter() {
+            let controls = instantiate_test_controls(&[
+                "--stack--", "name: stack1", format!("direction: {}", direction).as_str(), "spacing: 10", "padding: 5", "controls: box1;box2", "--end--",
+                "--picture_box--", "name: box1", "size: 100;20", "--end--",
+                "--picture_box--", "name: box2", "size: 60;40", "--end--"
+            ]);
+            let stack = controls.get_by_name("stack1".to_string());
+
+            assert_eq!(calculate_container_content_size(stack, &controls, Vec2::new(1920.0, 1080.0)), *expected, "direction: {}", direction);
+        }
+    }
+
+    #[test]
+    fn calculate_container_content_size_of_a_grid_only_counts_used_columns_and_rows() {
+        let cases = [("1", Vec2::new(64.0, 204.0)), ("2", Vec2::new(132.0, 134.0)), ("3", Vec2::new(200.0, 64.0)), ("4", Vec2::new(200.0, 64.0))];
+        for (columns, expected) in cases.iter() {
+            let controls = instantiate_test_controls(&[
+                "--grid--", "name: grid1", format!("columns: {}", columns).as_str(), "cell_size: 64;64", "spacing: 4;6", "controls: box1;box2;box3", "--end--",
+                "--picture_box--", "name: box1", "size: 32;32", "--end--",
+                "--picture_box--", "name: box2", "size: 32;32", "--end--",
+                "--picture_box--", "name: box3", "size: 32;32", "--end--"
+            ]);
+            let grid = controls.get_by_name("grid1".to_string());
+
+            assert_eq!(calculate_container_content_size(grid, &controls, Vec2::new(1920.0, 1080.0)), *expected, "columns: {}", columns);
+        }
+    }
+
+    #[test]
+    fn parse_safe_area_one_value_is_used_for_all_sides() {
+        let safe_area = parse_safe_area(&"20".to_string());
+
+        assert_eq!(safe_area.top, Val::Px(20.0));
+        assert_eq!(safe_area.right, Val::Px(20.0));
+        assert_eq!(safe_area.bottom, Val::Px(20.0));
+        assert_eq!(safe_area.left, Val::Px(20.0));
+    }
+
+    #[test]
+    fn parse_safe_area_two_values_are_top_bottom_then_left_right() {
+        let safe_area = parse_safe_area(&"10;5%".to_string());
+
+        assert_eq!(safe_area.top, Val::Px(10.0));
+        assert_eq!(safe_area.bottom, Val::Px(10.0));
+        assert_eq!(safe_area.left, Val::Percent(5.0));
+        assert_eq!(safe_area.right, Val::Percent(5.0));
+    }
+
+    #[test]
+    fn parse_safe_area_four_values_are_top_right_bottom_left() {
+        let safe_area = parse_safe_area(&"1;2;3;4".to_string());
+
+        assert_eq!(safe_area.top, Val::Px(1.0));
+        assert_eq!(safe_area.right, Val::Px(2.0));
+        assert_eq!(safe_area.bottom, Val::Px(3.0));
+        assert_eq!(safe_area.left, Val::Px(4.0));
+    }
+
+    #[test]
+    fn parse_aspect_ratio_takes_a_ratio_or_width_and_height() {
+        let cases = [("16:9", 16.0 / 9.0), (" 4 : 3 ", 4.0 / 3.0), ("2", 2.0), ("0.5", 0.5)];
+        for (s, expected) in cases.iter() {
+            assert_eq!(parse_aspect_ratio(&s.to_string()), *expected, "{}", s);
+        }
+    }
+
+    #[test]
+    fn apply_size_constraints_clamps_then_keeps_the_aspect_ratio() {
+        // fields, size, expected (relative to 200x200)
+        let cases = [
+            (vec!["min_size: 50;50"], Vec2::new(20.0, 80.0), Vec2::new(50.0, 80.0)),
+            (vec!["max_size: 50%;100"], Vec2::new(300.0, 300.0), Vec2::new(100.0, 100.0)),
+            (vec!["aspect_ratio: 2"], Vec2::new(300.0, 100.0), Vec2::new(200.0, 100.0)),
+            (vec!["aspect_ratio: 2"], Vec2::new(100.0, 100.0), Vec2::new(100.0, 50.0)),
+            (vec!["max_size: 100;100", "aspect_ratio: 1:2"], Vec2::new(300.0, 300.0), Vec2::new(50.0, 100.0))
+        ];
+        for (fields, size, expected) in cases.iter() {
+            let mut lines = vec!["--picture_box--", "name: box1", "size: 10;10"];
+            lines.extend(fields.iter());
+            lines.push("--end--");
+            let controls = instantiate_test_controls(&lines);
+            let control = controls.get_by_name("box1".to_string());
+
+            assert_eq!(apply_size_constraints(control, *size, Vec2::new(200.0, 200.0)), *expected, "{:?}", fields);
+        }
+    }
+
+    #[test]
+    fn get_focus_neighbour_maps_nav_fields_to_entities() {
+        let controls = instantiate_test_controls(&["--button--", "name: play", "nav_down: quit", "--end--", "--button--", "name: quit", "nav_up: play", "--end--"]);
+        let mut entities = HashMap::new();
+        entities.insert("play".to_string(), Entity::new(1));
+        entities.insert("quit".to_string(), Entity::new(2));
+
+        let cases = [("play", "nav_down", Some(Entity::new(2))), ("play", "nav_up", None), ("quit", "nav_up", Some(Entity::new(1))), ("quit", "nav_right", None)];
+        for (name, field_name, expected) in cases.iter() {
+            let control = controls.get_by_name(name.to_string());
+
+            assert_eq!(get_focus_neighbour(control, field_name, &entities), *expected, "{}.{}", name, field_name);
+        }
+    }
+
+    #[test]
+    fn parse_click_actions_reads_each_action_and_its_argument() {
+        let cases = [
+            ("", vec![]),
+            ("close_group(screen1.ui)", vec![ClickAction::CloseGroup("screen1.ui".to_string())]),
+            (" emit( start_game ) ; toggle(panel_x);", vec![ClickAction::Emit("start_game".to_string()), ClickAction::Toggle("panel_x".to_string())]),
+            ("open_screen(options.ui);call(quit)", vec![ClickAction::OpenScreen("options.ui".to_string()), ClickAction::Call("quit".to_string())])
+        ];
+        for (s, expected) in cases.iter() {
+            assert_eq!(parse_click_actions(&s.to_string()), *expected, "{}", s);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_click_actions_rejects_unknown_actions() {
+        parse_click_actions(&"explode(now)".to_string());
+    }
+
+    #[test]
+    #[should_panic(expected = "which is not found")]
+    fn open_screen_of_a_missing_file_is_rejected_when_parsed() {
+        instantiate_test_controls(&["--button--", "name: play", "on_click: open_screen(missing.ui)", "--end--"]);
+    }
+
+    #[test]
+    fn parse_polygon_reads_x_y_pairs() {
+        let cases = [("", vec![]), ("0;0; 10;0; 0;10", vec![Vec2::new(0.0, 0.0), Vec2::new(10.0, 0.0), Vec2::new(0.0, 10.0)])];
+        for (s, expected) in cases.iter() {
+            assert_eq!(parse_polygon(&s.to_string()), *expected, "{}", s);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn parse_polygon_needs_three_points() {
+        parse_polygon(&"0;0;10;0".to_string());
+    }
+
+    #[test]
+    fn cursor_position_overlaps_control_polygon_uses_the_even_odd_rule() {
+        // a U shape, so the notch between its arms is outside
+        let polygon = shapes::Polygon { points: parse_polygon(&"0;0; 30;0; 30;30; 20;30; 20;10; 10;10; 10;30; 0;30".to_string()) };
+        let cases = [(Vec2::new(5.0, 5.0), true), (Vec2::new(15.0, 5.0), true), (Vec2::new(25.0, 20.0), true), (Vec2::new(15.0, 20.0), false), (Vec2::new(35.0, 5.0), false), (Vec2::new(15.0, -5.0), false)];
+        for (position, expected) in cases.iter() {
+            assert_eq!(plugin::cursor_position_overlaps_control_polygon(*position, &polygon), *expected, "{:?}", position);
+        }
+    }
+}
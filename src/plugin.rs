@@ -1,88 +1,767 @@
+use std::collections::{HashMap, HashSet};
 use bevy::prelude::*;
+use bevy::input::InputSystem;
+use bevy::input::mouse::MouseWheel;
+use bevy::render::texture::TextureFormat;
+use bevy::text::Text2dSize;
+use bevy::window::{WindowId, WindowResized};
 
-use crate::shapes::Circle;
+use crate::{Controls, instantiate_controls_from_file, spawn_control_group, calculate_scale, calculate_size, calculate_top_left_position, get_center_position, get_label_size_constraints, get_parent_position, to_local_transform};
+use crate::shapes::{Circle, Polygon};
 
-pub struct ControlsPlugin {}
+#[derive(Default)]
+pub struct ControlsPlugin {
+    insert_click_markers: bool // also insert the ButtonClicked/ButtonLongPressed markers, kept for one frame
+}
+impl ControlsPlugin {
+    pub fn with_click_markers() -> Self {
+        let result = ControlsPlugin { insert_click_markers: true };
+
+        result
+    }
+}
 impl Plugin for ControlsPlugin {
     fn build(&self, app: &mut AppBuilder) {
         app
-            .add_system(button_click_check_system.system())
-            .add_system(button_hover_system.system())
+            .init_resource::<SafeArea>()
+            .init_resource::<GergFocus>()
+            .init_resource::<ActionRegistry>()
+            .init_resource::<UiInputState>()
+            .init_resource::<PointerTargets>()
+            .insert_resource(ClickMarkers { enabled: self.insert_click_markers })
+            .add_event::<GergButtonClicked>()
+            .add_event::<GergButtonLongPressed>()
+            .add_event::<GergActionEmitted>()
+            .add_event::<SetButtonEnabled>()
+            .add_event::<ControlHoverEntered>()
+            .add_event::<ControlHoverExited>()
+            // in CoreStage::First the cameras' GlobalTransform is the one propagated in the last CoreStage::PostUpdate, so the one the last frame was drawn with
+            .add_system_to_stage(CoreStage::First, group_camera_system.system())
+            .add_system_to_stage(CoreStage::PreUpdate, pointer_target_system.system().label(GergUiSystem::PointerTargets).label(GergUiSystem::InputState).after(InputSystem))
+            .add_system_to_stage(CoreStage::PreUpdate, ui_input_state_system.system().label(GergUiSystem::InputState).after(GergUiSystem::PointerTargets))
+            .add_system(button_click_check_system.system().label(GergUiSystem::Input))
+            .add_system(button_touch_system.system().label(GergUiSystem::Input))
+            .add_system(button_press_system.system().label(GergUiSystem::Input))
+            .add_system(button_hover_system.system().label(GergUiSystem::Input))
+            .add_system(default_focus_system.system())
+            .add_system(focus_lost_system.system().before(GergUiSystem::Input))
+            .add_system(focus_navigation_system.system().label(GergUiSystem::Input))
+            .add_system(focus_highlight_system.system())
             .add_system(button_cooldown_system.system())
+            .add_system(button_enabled_system.system())
+            .add_system(window_resized_system.system())
+            .add_system(safe_area_system.system())
+            .add_system(label_auto_size_system.system())
+            .add_system(control_group_layout_system.system())
+            .add_system(label_alignment_system.system())
+            .add_system(label_font_scale_system.system())
+            .add_system(world_label_alignment_system.system())
+            .add_system(control_visibility_system.system())
+            .add_system(click_markers_system.system())
+            .add_system(click_action_system.system())
             .add_system(destroy_controls_system.system());
     }
 }
 
+fn pointer_target_system(
+    windows: Res<Windows>,
+    touches: Res<Touches>,
+    materials: Res<Assets<ColorMaterial>>,
+    textures: Res<Assets<Texture>>,
+    mut texture_events: EventReader<AssetEvent<Texture>>,
+    mut alpha_masks: Local<HashMap<Handle<Texture>, AlphaMask>>,
+    mut pointer_targets: ResMut<PointerTargets>,
+    group_query: Query<(&GergControl, &GergControlGroup)>,
+    button_query: Query<(Entity, &Sprite, &GlobalTransform, &Visible, &GergButton, &GergControl)>,
+    blocker_query: Query<(Entity, &Sprite, &GlobalTransform, &Visible, &GergControl), (With<GergPictureBox>, With<BlocksInput>)>
+) {
+    // a changed texture has its alpha read again below, and a removed one is forgotten
+    for event in texture_events.iter() {
+        match event {
+            AssetEvent::Modified { handle } | AssetEvent::Removed { handle } => { alpha_masks.remove(handle); },
+            AssetEvent::Created { .. } => {}
+        }
+    }
+
+    // the alpha of each texture is only read once, as soon as it has loaded
+    for (_, _, _, _, button, _) in button_query.iter() {
+        if let HitTest::Alpha = button.hit_test {
+            if let Some(texture_handle) = get_texture_handle(&button.color_material_handle_normal, &materials) {
+                if !alpha_masks.contains_key(&texture_handle) {
+                    if let Some(texture) = textures.get(&texture_handle) {
+                        if let Some(alpha_mask) = create_alpha_mask(texture) {
+                            alpha_masks.insert(texture_handle, alpha_mask);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let group_cameras = get_group_cameras(&group_query);
+
+    pointer_targets.cursor = get_cursor_window_position(&windows).map(|(window_id, window_position)| {
+        let positions = get_group_positions(window_id, window_position, &group_cameras);
+        get_pointer_target(&windows, window_id, window_position, &positions, &button_query, &blocker_query, &materials, &alpha_masks)
+    });
+
+    pointer_targets.touches.clear();
+    // fingers lifted this frame still need their target, to finish presses (bevy only reports touches on the primary window)
+    for touch in touches.iter().chain(touches.iter_just_released()) {
+        let window_position = get_touch_window_position(&windows, touch.position());
+        let positions = get_group_positions(WindowId::primary(), window_position, &group_cameras);
+        let pointer_target = get_pointer_target(&windows, WindowId::primary(), window_position, &positions, &button_query, &blocker_query, &materials, &alpha_masks);
+        pointer_targets.touches.insert(touch.id(), pointer_target);
+    }
+
+    // a drag stays with the control its finger went down on, and is kept for the frame the finger is lifted in
+    pointer_targets.drags.retain(|id, _| touches.get_pressed(*id).is_some() || touches.just_released(*id));
+    for touch in touches.iter().chain(touches.iter_just_released()) {
+        let (entity, group_name) = match pointer_targets.drags.get(&touch.id()) {
+            Some(drag) => (drag.entity, drag.group_name.clone()),
+            None => {
+                let entity = pointer_targets.touches.get(&touch.id()).and_then(|target| target.entity);
+                (entity, entity.and_then(|entity| get_control_group_name(entity, &button_query, &blocker_query)))
+            }
+        };
+        let group_camera = group_name.as_ref().and_then(|group_name| group_cameras.get(group_name)).filter(|group_camera| group_camera.window_id == WindowId::primary());
+        let to_drag_position = |touch_position: Vec2| {
+            let window_position = get_touch_window_position(&windows, touch_position);
+            match group_camera {
+                Some(group_camera) => window_to_world_position(window_position, group_camera),
+                None => to_window_centered_position(&windows, WindowId::primary(), window_position)
+            }
+        };
+        let position = to_drag_position(touch.position());
+        let touch_drag = TouchDrag {
+            entity,
+            group_name,
+            start_position: to_drag_position(touch.start_position()),
+            position,
+            delta: position - to_drag_position(touch.previous_position())
+        };
+        pointer_targets.drags.insert(touch.id(), touch_drag);
+    }
+}
+
+fn get_control_group_name(entity: Entity, button_query: &Query<(Entity, &Sprite, &GlobalTransform, &Visible, &GergButton, &GergControl)>, blocker_query: &Query<(Entity, &Sprite, &GlobalTransform, &Visible, &GergControl), (With<GergPictureBox>, With<BlocksInput>)>) -> Option<String> {
+    let result = match button_query.get(entity) {
+        Ok((_, _, _, _, _, control)) => Some(control.group_name.clone()),
+        Err(_) => blocker_query.get(entity).ok().map(|(_, _, _, _, control)| control.group_name.clone())
+    };
+
+    result
+}
+
+fn get_texture_handle(color_material_handle: &Handle<ColorMaterial>, materials: &Assets<ColorMaterial>) -> Option<Handle<Texture>> {
+    let result = materials.get(color_material_handle).and_then(|material| material.texture.clone());
+
+    result
+}
+
+// each control group is hit tested through the camera that draws it, and laid out for that camera's window
+fn group_camera_system(
+    windows: Res<Windows>,
+    mut warned_group_names: Local<HashSet<String>>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    mut group_query: Query<(&GergControl, &mut GergControlGroup)>
+) {
+    for (control, mut group) in group_query.iter_mut() {
+        let camera_name = &group.controls.global_settings.camera;
+        let named_cameras = camera_query
+            .iter()
+            .filter(|(camera, _)| camera.name.as_ref() == Some(camera_name))
+            .collect::<Vec<(&Camera, &GlobalTransform)>>();
+        // every new_2d camera is called camera_2d, so with a camera per window the group's own window decides
+        let camera = named_cameras.iter().find(|(camera, _)| camera.window == group.window).or(named_cameras.first()).copied();
+
+        if camera.is_none() && warned_group_names.insert(control.group_name.clone()) {
+            warn!("Camera [{}] of control group [{}] not found, so the pointer is mapped relative to the middle of its window.", camera_name, control.group_name);
+        }
+
+        let window_id = camera.map_or(group.window, |(camera, _)| camera.window);
+        let window = match windows.get(window_id) {
+            Some(window) => window,
+            None => continue // closed
+        };
+        let window_size = Vec2::new(window.width(), window.height());
+        let world_from_ndc = match camera {
+            Some((camera, camera_transform)) => camera_transform.compute_matrix() * camera.projection_matrix.inverse(),
+            None => Mat4::from_scale((window_size / 2.0).extend(1.0))
+        };
+
+        if group.window != window_id {
+            // the camera draws into another window than the group was spawned for
+            group.window = window_id;
+            group.screen_size = window_size;
+            group.layout_changed = true;
+        }
+
+        let group_camera = Some(GroupCamera { window_id, window_size, world_from_ndc });
+        if group.camera != group_camera {
+            group.camera = group_camera;
+        }
+    }
+}
+
+fn get_group_cameras(group_query: &Query<(&GergControl, &GergControlGroup)>) -> HashMap<String, GroupCamera> {
+    let result = group_query
+        .iter()
+        .filter_map(|(control, group)| group.camera.map(|group_camera| (control.group_name.clone(), group_camera)))
+        .collect();
+
+    result
+}
+
+// where a position in a window lands in the world of every group drawn in that window
+fn get_group_positions(window_id: WindowId, window_position: Vec2, group_cameras: &HashMap<String, GroupCamera>) -> HashMap<String, Vec2> {
+    let result = group_cameras
+        .iter()
+        .filter(|(_, group_camera)| group_camera.window_id == window_id)
+        .map(|(group_name, group_camera)| (group_name.clone(), window_to_world_position(window_position, group_camera)))
+        .collect();
+
+    result
+}
+
+// window positions are in logical pixels from the bottom left, like bevy's cursor position, so HiDPI scale factors drop out
+fn window_to_world_position(window_position: Vec2, group_camera: &GroupCamera) -> Vec2 {
+    let ndc_position = window_position / group_camera.window_size * 2.0 - Vec2::new(1.0, 1.0);
+    let world_position = group_camera.world_from_ndc.transform_point3(ndc_position.extend(0.0));
+
+    world_position.truncate()
+}
+
+fn get_pointer_target(windows: &Windows, window_id: WindowId, window_position: Vec2, positions: &HashMap<String, Vec2>, button_query: &Query<(Entity, &Sprite, &GlobalTransform, &Visible, &GergButton, &GergControl)>, blocker_query: &Query<(Entity, &Sprite, &GlobalTransform, &Visible, &GergControl), (With<GergPictureBox>, With<BlocksInput>)>, materials: &Assets<ColorMaterial>, alpha_masks: &HashMap<Handle<Texture>, AlphaMask>) -> PointerTarget {
+    let result = match get_topmost_control(positions, button_query, blocker_query, materials, alpha_masks) {
+        Some((entity, position)) => PointerTarget { position, entity: Some(entity) },
+        None => PointerTarget { position: to_window_centered_position(windows, window_id, window_position), entity: None }
+    };
+
+    result
+}
+
+// nothing to map it through, so relative to the middle of the window
+fn to_window_centered_position(windows: &Windows, window_id: WindowId, window_position: Vec2) -> Vec2 {
+    let window_size = windows.get(window_id).map(|window| Vec2::new(window.width(), window.height())).unwrap_or_default();
+
+    window_position - window_size / 2.0
+}
+
+// highest draw_order wins, and buttons win ties against the picture boxes they sit on
+fn get_topmost_control(positions: &HashMap<String, Vec2>, button_query: &Query<(Entity, &Sprite, &GlobalTransform, &Visible, &GergButton, &GergControl)>, blocker_query: &Query<(Entity, &Sprite, &GlobalTransform, &Visible, &GergControl), (With<GergPictureBox>, With<BlocksInput>)>, materials: &Assets<ColorMaterial>, alpha_masks: &HashMap<Handle<Texture>, AlphaMask>) -> Option<(Entity, Vec2)> {
+    let buttons = button_query
+        .iter()
+        .filter(|(_, _, _, visible, _, _)| visible.is_visible)
+        .filter_map(|(entity, sprite, transform, _, button, control)| {
+            let position = *positions.get(&control.group_name)?;
+            if !cursor_position_overlaps_button(position, button, sprite, transform) { return None; }
+
+            let overlaps = match button.hit_test {
+                HitTest::Shape => true,
+                HitTest::Alpha => {
+                    let alpha_mask = get_texture_handle(&button.color_material_handle_normal, materials).and_then(|texture_handle| alpha_masks.get(&texture_handle));
+                    cursor_position_overlaps_control_alpha(to_local_position(position, transform), alpha_mask, button.alpha_threshold, sprite)
+                }
+            };
+
+            if overlaps { Some((entity, position, transform.translation.z, 1)) } else { None }
+        });
+    let blockers = blocker_query
+        .iter()
+        .filter(|(_, _, _, visible, _)| visible.is_visible)
+        .filter_map(|(entity, sprite, transform, _, control)| {
+            let position = *positions.get(&control.group_name)?;
+            if !cursor_position_overlaps_sprite(position, sprite, transform) { return None; }
+
+            Some((entity, position, transform.translation.z, 0))
+        });
+
+    let topmost = buttons
+        .chain(blockers)
+        .max_by(|a, b| a.2.partial_cmp(&b.2).unwrap_or(std::cmp::Ordering::Equal).then(a.3.cmp(&b.3)));
+
+    topmost.map(|(entity, position, _, _)| (entity, position))
+}
+
+fn ui_input_state_system(
+    windows: Res<Windows>,
+    mouse_input: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    touches: Res<Touches>,
+    focus: Res<GergFocus>,
+    pointer_targets: Res<PointerTargets>,
+    mut mouse_wheel_events: EventReader<MouseWheel>,
+    mut ui_input_state: ResMut<UiInputState>,
+    group_query: Query<(&GergControl, &GergControlGroup)>,
+    picture_box_query: Query<(&Sprite, &GlobalTransform, &Visible, &GergControl), (With<GergPictureBox>, Without<BlocksInput>)>,
+    focusable_query: Query<(&Visible, &GergButton)>
+) {
+    let group_cameras = get_group_cameras(&group_query);
+
+    // buttons and blocking picture boxes were hit tested with the pointer targets, the other picture boxes (e.g. a panel's background) still take the input
+    let cursor_over_target = pointer_targets.cursor.map_or(false, |cursor| cursor.entity.is_some());
+    let pointer_over_control = cursor_over_target || match get_cursor_window_position(&windows) {
+        Some((window_id, window_position)) => {
+            is_over_any_picture_box(&get_group_positions(window_id, window_position, &group_cameras), &picture_box_query)
+        },
+        None => false
+    };
+    let touched_control = touches.iter_just_pressed().any(|touch| {
+        let touch_over_target = pointer_targets.touches.get(&touch.id()).map_or(false, |target| target.entity.is_some());
+        let window_position = get_touch_window_position(&windows, touch.position());
+
+        touch_over_target || is_over_any_picture_box(&get_group_positions(WindowId::primary(), window_position, &group_cameras), &picture_box_query)
+    });
+
+    let navigation_keys = [KeyCode::Up, KeyCode::Down, KeyCode::Left, KeyCode::Right, KeyCode::Return, KeyCode::NumpadEnter];
+    // the focus may still name a button that was destroyed, hidden or disabled since
+    let focused = match focus.entity.map(|entity| focusable_query.get(entity)) {
+        Some(Ok((visible, button))) => is_focusable(visible, button),
+        _ => false
+    };
+    let navigated = focused && navigation_keys.iter().any(|key| keyboard_input.just_pressed(*key));
+    // Tab focuses the first button even when nothing is focused yet
+    let tabbed = keyboard_input.just_pressed(KeyCode::Tab) && focusable_query.iter().any(|(visible, button)| is_focusable(visible, button));
+
+    ui_input_state.pointer_over_control = pointer_over_control;
+    ui_input_state.click_consumed = (pointer_over_control && mouse_input.get_just_pressed().next().is_some()) || touched_control;
+    ui_input_state.keyboard_consumed = navigated || tabbed;
+    ui_input_state.scroll_consumed = pointer_over_control && mouse_wheel_events.iter().next().is_some();
+}
+
+fn is_over_any_picture_box(positions: &HashMap<String, Vec2>, picture_box_query: &Query<(&Sprite, &GlobalTransform, &Visible, &GergControl), (With<GergPictureBox>, Without<BlocksInput>)>) -> bool {
+    let result = picture_box_query.iter().any(|(sprite, transform, visible, control)| {
+        match positions.get(&control.group_name) {
+            Some(position) => visible.is_visible && cursor_position_overlaps_sprite(*position, sprite, transform),
+            None => false
+        }
+    });
+
+    result
+}
+
 fn button_cooldown_system(
     mut commands: Commands,
     time: Res<Time>,
+    pointer_targets: Res<PointerTargets>,
     mut control_query: Query<(Entity, &mut Handle<ColorMaterial>, &mut Cooldown, &mut GergButton)>
 ) {
+    let hovered_entity = pointer_targets.cursor.and_then(|cursor| cursor.entity);
+
     for (entity, mut color_material, mut cooldown, mut button) in control_query.iter_mut() {
         cooldown.remaining_time_in_seconds -= time.delta_seconds();
+        cooldown.remaining_active_time_in_seconds -= time.delta_seconds();
 
-        if cooldown.remaining_time_in_seconds <= 0.0 {
-            // change to normal
-            button.button_state = ButtonState::Normal;
-            *color_material = button.color_material_handle_normal.clone();
+        // a button pressed again meanwhile is no longer showing its click
+        if cooldown.remaining_active_time_in_seconds <= 0.0 && matches!(button.button_state, ButtonState::Active) {
+            if hovered_entity == Some(entity) {
+                // change to hover
+                button.button_state = ButtonState::Hover;
+                *color_material = button.color_material_handle_hover.clone();
+            } else {
+                // change to normal
+                button.button_state = ButtonState::Normal;
+                *color_material = button.color_material_handle_normal.clone();
+            }
+        }
 
+        if cooldown.remaining_time_in_seconds <= 0.0 && cooldown.remaining_active_time_in_seconds <= 0.0 {
             commands.entity(entity).remove::<Cooldown>();
         }
     }
 }
 
+fn is_cooling_down(cooldown: Option<&Cooldown>) -> bool {
+    let result = match cooldown {
+        Some(cooldown) => cooldown.remaining_time_in_seconds > 0.0,
+        None => false
+    };
+
+    result
+}
+
 fn button_click_check_system(
     mut commands: Commands,
-    windows: Res<Windows>,
     mouse_input: Res<Input<MouseButton>>,
+    pointer_targets: Res<PointerTargets>,
+    time: Res<Time>,
     audio: Res<Audio>,
     asset_server: Res<AssetServer>,
-    mut control_query: Query<(Entity, &Sprite, &Transform, &mut Handle<ColorMaterial>, &mut GergButton)>
+    mut button_clicked_events: EventWriter<GergButtonClicked>,
+    mut control_query: Query<(Entity, &mut Handle<ColorMaterial>, &mut GergButton, &GergControl, Option<&Cooldown>)>
 ) {
-    if mouse_input.just_pressed(MouseButton::Left) {
-        let cursor_position = get_cursor_position(windows);
+    let cursor = match pointer_targets.cursor {
+        Some(cursor) => cursor,
+        None => return // outside the window
+    };
+    let cursor_position = cursor.position;
+
+    for mouse_button in mouse_input.get_just_pressed() {
+        for (entity, mut color_material, mut button, control, cooldown) in control_query.iter_mut() {
+            if button.on_click_button.is_some() && button.on_click_button != Some(*mouse_button) { continue; }
 
-        for (entity, sprite, transform, mut color_material, mut button) in control_query.iter_mut() {
-            let control_bounding_shapes = get_control_bounding_shapes(button.bounding_box, button.bounding_circle, sprite, transform);
-    
-            // if mouse is over control
-            let collision = cursor_position_overlaps_control_bounding_shapes(cursor_position, control_bounding_shapes);
+            // if mouse is over control (and no other control is on top of it)
+            let collision = cursor.entity == Some(entity);
             if collision {
-                match button.button_state {
-                    ButtonState::Hover => {
-                        // change to active
-                        button.button_state = ButtonState::Active;
-                        *color_material = button.color_material_handle_active.clone();
-
-                        let sound = &button.on_click_sound;
-                        if !sound.is_empty() {
-                            let sound_effect = asset_server.load(sound.as_str());
-                            audio.play(sound_effect);
+                let clickable = match button.button_state {
+                    // the second click of a double click doesn't wait for the cooldown
+                    ButtonState::Hover | ButtonState::Active => !is_cooling_down(cooldown) || is_double_click(&button, *mouse_button, &time),
+                    _ => false
+                };
+
+                if clickable {
+                    match button.activate_on {
+                        ActivateOn::Press => {
+                            click_button(&mut commands, entity, &mut color_material, &mut button, control, *mouse_button, cursor_position, &time, &audio, &asset_server, &mut button_clicked_events);
+                            track_hold(&mut commands, entity, &button, Pointer::Mouse(*mouse_button));
+                        },
+                        ActivateOn::Release => press_button(&mut commands, entity, &mut color_material, &mut button, Pointer::Mouse(*mouse_button))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn button_touch_system(
+    mut commands: Commands,
+    touches: Res<Touches>,
+    pointer_targets: Res<PointerTargets>,
+    time: Res<Time>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    mut button_clicked_events: EventWriter<GergButtonClicked>,
+    mut control_query: Query<(Entity, &mut Handle<ColorMaterial>, &mut GergButton, &GergControl, Option<&Cooldown>)>
+) {
+    // every finger that went down this frame is a tap of its own, fingers can't hover so none is needed first
+    for touch in touches.iter_just_pressed() {
+        let target = match pointer_targets.touches.get(&touch.id()) {
+            Some(target) => *target,
+            None => continue
+        };
+        let touch_position = target.position;
+
+        for (entity, mut color_material, mut button, control, cooldown) in control_query.iter_mut() {
+            // if finger is over control (and no other control is on top of it)
+            let collision = target.entity == Some(entity);
+            if collision {
+                let clickable = match button.button_state {
+                    ButtonState::Normal | ButtonState::Hover | ButtonState::Active => !is_cooling_down(cooldown) || is_double_click(&button, MouseButton::Left, &time),
+                    _ => false
+                };
+
+                if clickable {
+                    match button.activate_on {
+                        ActivateOn::Press => {
+                            click_button(&mut commands, entity, &mut color_material, &mut button, control, MouseButton::Left, touch_position, &time, &audio, &asset_server, &mut button_clicked_events);
+                            track_hold(&mut commands, entity, &button, Pointer::Touch(touch.id()));
+                        },
+                        ActivateOn::Release => press_button(&mut commands, entity, &mut color_material, &mut button, Pointer::Touch(touch.id()))
+                    }
+                }
+            }
+        }
+    }
+}
+
+fn button_press_system(
+    mut commands: Commands,
+    mouse_input: Res<Input<MouseButton>>,
+    touches: Res<Touches>,
+    pointer_targets: Res<PointerTargets>,
+    time: Res<Time>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    mut button_clicked_events: EventWriter<GergButtonClicked>,
+    mut button_long_pressed_events: EventWriter<GergButtonLongPressed>,
+    mut control_query: Query<(Entity, &mut Handle<ColorMaterial>, &mut GergButton, &GergControl, &mut ButtonPress)>
+) {
+    for (entity, mut color_material, mut button, control, mut press) in control_query.iter_mut() {
+        if let ButtonState::Disabled = button.button_state {
+            commands.entity(entity).remove::<ButtonPress>();
+            continue;
+        }
+
+        // what the pointer is over now, and whether it has been let go
+        let (target, released) = match press.pointer {
+            Pointer::Mouse(mouse_button) => {
+                // a press stays tracked while the cursor is outside the window, it only counts as off the button when let go out there
+                let released = !mouse_input.pressed(mouse_button);
+                match pointer_targets.cursor {
+                    Some(cursor) => (Some(cursor), released),
+                    None if released => (None, true),
+                    None => continue
+                }
+            },
+            // a cancelled touch has no target any more
+            Pointer::Touch(id) => (pointer_targets.touches.get(&id).copied(), touches.get_pressed(id).is_none())
+        };
+
+        let collision = matches!(target, Some(target) if target.entity == Some(entity));
+        let position = target.map(|target| target.position).unwrap_or_default(); // only used while over the button
+
+        let mouse_button = match press.pointer {
+            Pointer::Mouse(mouse_button) => mouse_button,
+            Pointer::Touch(_) => MouseButton::Left
+        };
+
+        if !collision {
+            // dragged off, so the press is cancelled
+            if let ButtonState::Pressed = button.button_state {
+                button.button_state = ButtonState::Normal;
+                *color_material = button.color_material_handle_normal.clone();
+            }
+            commands.entity(entity).remove::<ButtonPress>();
+        } else if released {
+            commands.entity(entity).remove::<ButtonPress>();
+            if press.click_on_release {
+                click_button(&mut commands, entity, &mut color_material, &mut button, control, mouse_button, position, &time, &audio, &asset_server, &mut button_clicked_events);
+            } else if let ButtonState::Pressed = button.button_state {
+                button.button_state = ButtonState::Normal;
+                *color_material = button.color_material_handle_normal.clone();
+            }
+        } else {
+            press.held_time_in_seconds += time.delta_seconds();
+
+            if button.long_press_time > 0.0 && !press.long_pressed && press.held_time_in_seconds >= button.long_press_time {
+                // with activate_on: release the long press replaces the click the release would have made, with press the click was already sent on press
+                press.long_pressed = true;
+                press.click_on_release = false;
+                button_long_pressed_events.send(GergButtonLongPressed {
+                    entity,
+                    name: button.name.clone(),
+                    group_name: control.group_name.clone(),
+                    position
+                });
+            }
+
+            let next_repeat_time = button.repeat_delay + press.repeat_count as f32 * button.repeat_interval;
+            if button.repeat_interval > 0.0 && press.held_time_in_seconds >= next_repeat_time {
+                press.repeat_count += 1;
+                press.click_on_release = false;
+                // repeats are never double clicks
+                button.last_click = None;
+                click_button(&mut commands, entity, &mut color_material, &mut button, control, mouse_button, position, &time, &audio, &asset_server, &mut button_clicked_events);
+            }
+        }
+    }
+}
+
+fn button_enabled_system(
+    mut commands: Commands,
+    mut set_button_enabled_events: EventReader<SetButtonEnabled>,
+    mut control_query: Query<(Entity, &mut Handle<ColorMaterial>, &mut GergButton, &GergControl)>
+) {
+    for event in set_button_enabled_events.iter() {
+        for (entity, mut color_material, mut button, control) in control_query.iter_mut() {
+            let is_target = match &event.target {
+                ButtonTarget::Entity(target) => *target == entity,
+                ButtonTarget::Name { name, group_name } => button.name == *name && control.group_name == *group_name
+            };
+            if !is_target { continue; }
+
+            let disabled = matches!(button.button_state, ButtonState::Disabled);
+            if event.enabled != disabled { continue; } // nothing to switch
+
+            if event.enabled {
+                // change to normal, the hover system picks it up again if the cursor is over it
+                button.button_state = ButtonState::Normal;
+                *color_material = button.color_material_handle_normal.clone();
+            } else {
+                // change to disabled, dropping any click or press in progress
+                button.button_state = ButtonState::Disabled;
+                *color_material = button.color_material_handle_disabled.clone();
+                commands.entity(entity).remove::<Cooldown>();
+                commands.entity(entity).remove::<ButtonPress>();
+            }
+        }
+    }
+}
+
+fn press_button(commands: &mut Commands, entity: Entity, color_material: &mut Handle<ColorMaterial>, button: &mut GergButton, pointer: Pointer) {
+    // change to pressed, the click waits for the release
+    button.button_state = ButtonState::Pressed;
+    *color_material = button.color_material_handle_active.clone();
+
+    commands.entity(entity).remove::<Cooldown>();
+    commands.entity(entity).insert(ButtonPress::new(pointer, true));
+}
+
+fn track_hold(commands: &mut Commands, entity: Entity, button: &GergButton, pointer: Pointer) {
+    // buttons clicked on press only need following while held for repeats and long presses
+    if button.repeat_interval > 0.0 || button.long_press_time > 0.0 {
+        commands.entity(entity).insert(ButtonPress::new(pointer, false));
+    }
+}
+
+fn click_button(commands: &mut Commands, entity: Entity, color_material: &mut Handle<ColorMaterial>, button: &mut GergButton, control: &GergControl, mouse_button: MouseButton, position: Vec2, time: &Time, audio: &Audio, asset_server: &AssetServer, button_clicked_events: &mut EventWriter<GergButtonClicked>) {
+    let double_click = is_double_click(button, mouse_button, time);
+    // a third click starts a new double click rather than completing another one
+    button.last_click = if double_click { None } else { Some((mouse_button, time.seconds_since_startup())) };
+
+    // change to active
+    button.button_state = ButtonState::Active;
+    *color_material = button.color_material_handle_active.clone();
+
+    let sound = &button.on_click_sound;
+    if !sound.is_empty() {
+        let sound_effect = asset_server.load(sound.as_str());
+        audio.play(sound_effect);
+    }
+
+    commands.entity(entity).insert(Cooldown { remaining_time_in_seconds: button.cooldown, remaining_active_time_in_seconds: button.active_display_time });
+
+    button_clicked_events.send(GergButtonClicked {
+        entity,
+        name: button.name.clone(),
+        group_name: control.group_name.clone(),
+        mouse_button,
+        position,
+        double_click
+    });
+}
+
+fn click_action_system(
+    mut commands: Commands,
+    windows: Res<Windows>,
+    asset_server: Res<AssetServer>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    action_registry: Res<ActionRegistry>,
+    mut button_clicked_events: EventReader<GergButtonClicked>,
+    mut action_emitted_events: EventWriter<GergActionEmitted>,
+    button_query: Query<&GergButton>,
+    control_query: Query<(Entity, &GergControl)>,
+    group_query: Query<(&GergControl, &GergControlGroup)>,
+    mut visible_query: Query<&mut Visible>
+) {
+    for event in button_clicked_events.iter() {
+        let actions = match button_query.get(event.entity) {
+            Ok(button) => button.on_click.clone(),
+            Err(_) => continue // destroyed meanwhile
+        };
+
+        for action in actions {
+            match action {
+                ClickAction::CloseGroup(group_name) => {
+                    for (entity, control) in control_query.iter() {
+                        if control.group_name == group_name {
+                            commands.entity(entity).insert(DestroyControl);
                         }
+                    }
+                },
+                ClickAction::OpenScreen(filename) => {
+                    // a screen that is already open isn't opened twice
+                    if control_query.iter().any(|(_, control)| control.group_name == filename) { continue; }
 
-                        commands.entity(entity).insert(Cooldown { remaining_time_in_seconds: 0.5 });
-                        
-                        // TODO: call some sort of func/action delegate
-                        commands.entity(entity).insert(ButtonClicked);
-                    },
-                    _ => { } // do nothing
+                    // the new screen opens in the window of the button that opened it
+                    let window_id = group_query
+                        .iter()
+                        .find(|(control, _)| control.group_name == event.group_name)
+                        .map_or(WindowId::primary(), |(_, group)| group.window);
+                    let window = match windows.get(window_id) {
+                        Some(window) => window,
+                        None => continue // closed
+                    };
+                    let screen_size = Vec2::new(window.width(), window.height());
+                    let controls = instantiate_controls_from_file(&filename);
+                    spawn_control_group(&mut commands, &asset_server, &mut materials, controls, screen_size, window_id, filename);
+                },
+                ClickAction::Emit(name) => {
+                    action_emitted_events.send(GergActionEmitted { name, entity: event.entity, group_name: event.group_name.clone() });
+                },
+                ClickAction::Toggle(control_name) => {
+                    let group = group_query.iter().find(|(control, _)| control.group_name == event.group_name);
+                    if let Some((_, group)) = group {
+                        if let Some(entity) = group.entities.get(&control_name) {
+                            if let Ok(mut visible) = visible_query.get_mut(*entity) {
+                                visible.is_visible = !visible.is_visible;
+                            }
+                        }
+                    }
+                },
+                ClickAction::Call(name) => {
+                    match action_registry.actions.get(&name) {
+                        Some(action) => action(&mut commands, event),
+                        None => warn!("Action [{}] is not registered in the ActionRegistry, so button [{}] skips it.", name, event.name)
+                    }
                 }
             }
         }
     }
 }
 
+fn click_markers_system(
+    mut commands: Commands,
+    click_markers: Res<ClickMarkers>,
+    mut button_clicked_events: EventReader<GergButtonClicked>,
+    mut button_long_pressed_events: EventReader<GergButtonLongPressed>,
+    clicked_query: Query<Entity, With<ButtonClicked>>,
+    long_pressed_query: Query<Entity, With<ButtonLongPressed>>
+) {
+    if !click_markers.enabled { return; }
+
+    // commands are applied at the end of CoreStage::Update, so game systems see the markers from CoreStage::PostUpdate up to and including the next CoreStage::Update
+    for entity in clicked_query.iter() {
+        commands.entity(entity).remove::<ButtonClicked>();
+    }
+
+    for entity in long_pressed_query.iter() {
+        commands.entity(entity).remove::<ButtonLongPressed>();
+    }
+
+    for event in button_clicked_events.iter() {
+        commands.entity(event.entity).insert(ButtonClicked { mouse_button: event.mouse_button, double_click: event.double_click });
+    }
+
+    for event in button_long_pressed_events.iter() {
+        commands.entity(event.entity).insert(ButtonLongPressed);
+    }
+}
+
+fn is_double_click(button: &GergButton, mouse_button: MouseButton, time: &Time) -> bool {
+    let result = match button.last_click {
+        Some((last_mouse_button, last_click_time)) => {
+            last_mouse_button == mouse_button && time.seconds_since_startup() - last_click_time <= button.double_click_time as f64
+        },
+        None => false
+    };
+
+    result
+}
+
 fn button_hover_system(
-    windows: Res<Windows>,
-    mut control_query: Query<(&Sprite, &Transform, &mut Handle<ColorMaterial>, &mut GergButton)>
+    pointer_targets: Res<PointerTargets>,
+    focus: Res<GergFocus>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    mut hover_entered_events: EventWriter<ControlHoverEntered>,
+    mut hover_exited_events: EventWriter<ControlHoverExited>,
+    mut control_query: Query<(Entity, &mut Handle<ColorMaterial>, &mut GergButton, &GergControl)>
 ) {
-    let cursor_position = get_cursor_position(windows);
+    let hovered_entity = pointer_targets.cursor.and_then(|cursor| cursor.entity);
+
+    for (entity, mut color_material, mut button, control) in control_query.iter_mut() {
+        // if mouse is over control (and no other control is on top of it)
+        let collision = hovered_entity == Some(entity);
 
-    for (sprite, transform, mut color_material, mut button) in control_query.iter_mut() {
-        let control_bounding_shapes = get_control_bounding_shapes(button.bounding_box, button.bounding_circle, sprite, transform);
+        // disabled controls can't be hovered
+        let hovered = collision && !matches!(button.button_state, ButtonState::Disabled);
+        if hovered != button.hovered {
+            button.hovered = hovered;
+            if hovered {
+                let sound = &button.on_hover_sound;
+                if !sound.is_empty() {
+                    let sound_effect = asset_server.load(sound.as_str());
+                    audio.play(sound_effect);
+                }
+
+                hover_entered_events.send(ControlHoverEntered { entity, name: button.name.clone(), group_name: control.group_name.clone() });
+            } else {
+                hover_exited_events.send(ControlHoverExited { entity, name: button.name.clone(), group_name: control.group_name.clone() });
+            }
+        }
 
-        // if mouse is over control
-        let collision = cursor_position_overlaps_control_bounding_shapes(cursor_position, control_bounding_shapes);
         if collision {
             match button.button_state {
                 ButtonState::Normal => {
@@ -92,8 +771,8 @@ fn button_hover_system(
                 },
                 _ => { } // do nothing
             }
-        } else {
-            // else mouse is not over control
+        } else if focus.entity != Some(entity) {
+            // else mouse is not over control (and the focused control keeps its hover look)
             match button.button_state {
                 ButtonState::Hover => {
                     // change to normal
@@ -106,6 +785,441 @@ fn button_hover_system(
     }
 }
 
+fn window_resized_system(
+    mut window_resized_events: EventReader<WindowResized>,
+    mut group_query: Query<&mut GergControlGroup>
+) {
+    // only the last resize of each window this frame matters
+    let mut resized = HashMap::new();
+    for event in window_resized_events.iter() {
+        resized.insert(event.id, Vec2::new(event.width, event.height));
+    }
+
+    for mut group in group_query.iter_mut() {
+        if let Some(screen_size) = resized.get(&group.window) {
+            group.screen_size = *screen_size;
+            group.layout_changed = true;
+        }
+    }
+}
+
+fn safe_area_system(
+    safe_area: Res<SafeArea>,
+    mut group_query: Query<&mut GergControlGroup>
+) {
+    for mut group in group_query.iter_mut() {
+        if group.controls.safe_area_override != safe_area.margins {
+            group.controls.safe_area_override = safe_area.margins;
+            group.layout_changed = true;
+        }
+    }
+}
+
+fn label_auto_size_system(
+    ui_label_query: Query<(&GergLabel, &GergControl, &CalculatedSize), (With<AutoSize>, Changed<CalculatedSize>)>,
+    world_label_query: Query<(&GergLabel, &GergControl, &Text2dSize), (With<AutoSize>, Changed<Text2dSize>)>,
+    mut group_query: Query<(&GergControl, &mut GergControlGroup)>
+) {
+    for (label, label_control, calculated_size) in ui_label_query.iter() {
+        update_auto_sized_label(&mut group_query, label, label_control, calculated_size.size);
+    }
+
+    for (label, label_control, text_2d_size) in world_label_query.iter() {
+        update_auto_sized_label(&mut group_query, label, label_control, text_2d_size.size);
+    }
+}
+
+fn update_auto_sized_label(group_query: &mut Query<(&GergControl, &mut GergControlGroup)>, label: &GergLabel, label_control: &GergControl, measured_size: Size) {
+    for (group_control, mut group) in group_query.iter_mut() {
+        if group_control.group_name != label_control.group_name { continue; }
+
+        // the text was measured at the group's scale, but the layout works in unscaled sizes
+        let measured_size = format!("{};{}", measured_size.width / group.scale, measured_size.height / group.scale);
+
+        let control = group.controls.map.get_mut(&label.name).expect("Auto sized label not found in its control group.");
+        if *control.fields.get_by_name("size") != measured_size {
+            control.fields.insert("size".to_string(), measured_size.clone());
+            group.layout_changed = true;
+        }
+    }
+}
+
+fn control_group_layout_system(
+    mut group_query: Query<&mut GergControlGroup>,
+    mut transform_query: Query<&mut Transform, Without<LabelLayout>>,
+    mut label_query: Query<&mut LabelLayout>
+) {
+    for mut group in group_query.iter_mut() {
+        if !group.layout_changed { continue; }
+        group.layout_changed = false;
+        let scale = calculate_scale(&group.controls, group.screen_size);
+        group.scale = scale;
+        let layout_screen_size = group.screen_size / scale;
+
+        for (name, entity) in group.entities.iter() {
+            let control = group.controls.get_by_name(name.clone());
+            let size = calculate_size(control, &group.controls, layout_screen_size);
+            let top_left_position = calculate_top_left_position(control, &group.controls, layout_screen_size);
+            let parent_position = get_parent_position(control, &group.controls, layout_screen_size, scale);
+
+            if let Ok(mut label_layout) = label_query.get_mut(*entity) {
+                label_layout.top_left_position = top_left_position * scale;
+                label_layout.size = size * scale;
+                label_layout.screen_size = group.screen_size;
+                label_layout.scale = scale;
+                label_layout.parent_position = parent_position;
+            } else if let Ok(mut transform) = transform_query.get_mut(*entity) {
+                let center_position = get_center_position(top_left_position, size, scale).extend(transform.translation.z);
+                let (center_position, scale) = to_local_transform(center_position, scale, parent_position);
+                transform.translation.x = center_position.x;
+                transform.translation.y = center_position.y;
+                transform.scale = scale;
+            }
+        }
+    }
+}
+
+fn label_alignment_system(
+    mut label_query: Query<(&LabelLayout, &CalculatedSize, &mut Style), Or<(Changed<LabelLayout>, Changed<CalculatedSize>)>>
+) {
+    for (label_layout, calculated_size, mut style) in label_query.iter_mut() {
+        let text_size = Vec2::new(calculated_size.size.width, calculated_size.size.height);
+        let top_left_position = get_aligned_text_top_left_position(label_layout, text_size);
+        let left = Val::Px(top_left_position.x + label_layout.screen_size.x * 0.5);
+        let top = Val::Px(label_layout.screen_size.y * 0.5 - top_left_position.y);
+
+        let (min_size, max_size) = get_label_size_constraints(label_layout);
+
+        // only touch the style when it actually changes, as changing it makes bevy measure the text again
+        if style.position.left != left || style.position.top != top || style.min_size != min_size || style.max_size != max_size {
+            style.position.left = left;
+            style.position.top = top;
+            style.min_size = min_size;
+            style.max_size = max_size;
+        }
+    }
+}
+
+fn label_font_scale_system(
+    mut label_query: Query<(&LabelLayout, &mut Text), Changed<LabelLayout>>
+) {
+    for (label_layout, mut text) in label_query.iter_mut() {
+        let font_size = get_label_font_size(label_layout);
+
+        // only touch the text when the size actually changes, as changing it makes bevy measure the text again
+        if text.sections.iter().any(|section| section.style.font_size != font_size) {
+            for section in text.sections.iter_mut() {
+                section.style.font_size = font_size;
+            }
+        }
+    }
+}
+
+// a label docked as a child already inherits its parent's scale
+pub(crate) fn get_label_font_size(label_layout: &LabelLayout) -> f32 {
+    let result = match label_layout.parent_position {
+        Some(_) => label_layout.font_size,
+        None => label_layout.font_size * label_layout.scale
+    };
+
+    result
+}
+
+fn world_label_alignment_system(
+    mut label_query: Query<(&LabelLayout, &Text2dSize, &mut Transform), Or<(Changed<LabelLayout>, Changed<Text2dSize>)>>
+) {
+    for (label_layout, text_2d_size, mut transform) in label_query.iter_mut() {
+        let mut text_size = Vec2::new(text_2d_size.size.width, text_2d_size.size.height);
+        if label_layout.parent_position.is_some() {
+            // the text of a child is measured unscaled, but drawn with its parent's scale
+            text_size *= label_layout.scale;
+        }
+        let top_left_position = get_aligned_text_top_left_position(label_layout, text_size);
+
+        // bevy draws 2d text offset from its translation depending on the alignment, so undo that offset here
+        let horizontal_offset = match label_layout.horizontal_align {
+            HorizontalAlign::Left => -text_size.x,
+            HorizontalAlign::Center => -text_size.x * 0.5,
+            HorizontalAlign::Right => 0.0
+        };
+        let vertical_offset = match label_layout.vertical_align {
+            VerticalAlign::Top => 0.0,
+            VerticalAlign::Center => -text_size.y * 0.5,
+            VerticalAlign::Bottom => -text_size.y
+        };
+        let mut translation = Vec2::new(top_left_position.x - horizontal_offset, top_left_position.y - text_size.y - vertical_offset);
+        if let Some(parent_position) = label_layout.parent_position {
+            translation = (translation - parent_position.truncate()) / label_layout.scale;
+        }
+        transform.translation.x = translation.x;
+        transform.translation.y = translation.y;
+    }
+}
+
+// bevy doesn't pass visibility down to children, so do it for controls spawned with dock_as_child
+fn control_visibility_system(
+    mut visible_queries: QuerySet<(
+        Query<(Entity, &Visible), (With<GergControl>, With<Children>, Changed<Visible>)>,
+        Query<&mut Visible>
+    )>,
+    children_query: Query<&Children>
+) {
+    let changed = visible_queries.q0().iter().map(|(entity, visible)| (entity, visible.is_visible)).collect::<Vec<(Entity, bool)>>();
+
+    for (entity, is_visible) in changed {
+        let mut descendants = vec![entity];
+        while let Some(entity) = descendants.pop() {
+            if let Ok(children) = children_query.get(entity) {
+                for child in children.iter() {
+                    if let Ok(mut visible) = visible_queries.q1_mut().get_mut(*child) {
+                        if visible.is_visible != is_visible {
+                            visible.is_visible = is_visible;
+                        }
+                    }
+                    descendants.push(*child);
+                }
+            }
+        }
+    }
+}
+
+fn get_aligned_text_top_left_position(label_layout: &LabelLayout, text_size: Vec2) -> Vec2 {
+    let horizontal_factor = match label_layout.horizontal_align {
+        HorizontalAlign::Left => 0.0,
+        HorizontalAlign::Center => 0.5,
+        HorizontalAlign::Right => 1.0
+    };
+    let vertical_factor = match label_layout.vertical_align {
+        VerticalAlign::Top => 0.0,
+        VerticalAlign::Center => 0.5,
+        VerticalAlign::Bottom => 1.0
+    };
+    let x = label_layout.top_left_position.x + (label_layout.size.x - text_size.x) * horizontal_factor;
+    let y = label_layout.top_left_position.y - (label_layout.size.y - text_size.y) * vertical_factor;
+
+    Vec2::new(x, y)
+}
+
+fn focus_navigation_system(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    gamepad_input: Res<Input<GamepadButton>>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+    mut gamepads: Local<Vec<Gamepad>>,
+    mut stick_engaged: Local<bool>,
+    time: Res<Time>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    mut focus: ResMut<GergFocus>,
+    mut button_clicked_events: EventWriter<GergButtonClicked>,
+    default_focus_query: Query<Entity, With<DefaultFocus>>,
+    mut control_query: Query<(Entity, &GlobalTransform, &Visible, &mut Handle<ColorMaterial>, &mut GergButton, &GergControl, Option<&FocusNavigation>, Option<&Cooldown>)>
+) {
+    for event in gamepad_events.iter() {
+        match event.1 {
+            GamepadEventType::Connected => { if !gamepads.contains(&event.0) { gamepads.push(event.0); } },
+            GamepadEventType::Disconnected => { gamepads.retain(|gamepad| *gamepad != event.0); },
+            _ => { }
+        }
+    }
+
+    let focus_move = get_focus_move(&keyboard_input, &gamepad_input, &gamepad_axes, &gamepads, &mut stick_engaged);
+    let activate = keyboard_input.just_pressed(KeyCode::Return) || keyboard_input.just_pressed(KeyCode::NumpadEnter) ||
+        gamepad_input.get_just_pressed().any(|button| button.1 == GamepadButtonType::South);
+
+    if let Some(focus_move) = focus_move {
+        let candidates = control_query
+            .iter_mut()
+            .filter(|(_, _, visible, _, button, _, _, _)| is_focusable(visible, button))
+            .map(|(entity, transform, _, _, button, _, focus_navigation, _)| FocusCandidate {
+                entity,
+                position: transform.translation.truncate(),
+                tab_index: button.tab_index,
+                navigation: focus_navigation.cloned().unwrap_or_default()
+            })
+            .collect::<Vec<FocusCandidate>>();
+        let default_focus = default_focus_query.iter().next();
+
+        focus.entity = get_next_focus(focus.entity, default_focus, focus_move, candidates);
+    }
+
+    if activate {
+        if let Some(entity) = focus.entity {
+            if let Ok((entity, transform, _, mut color_material, mut button, control, _, cooldown)) = control_query.get_mut(entity) {
+                match button.button_state {
+                    ButtonState::Normal | ButtonState::Hover | ButtonState::Active if !is_cooling_down(cooldown) => {
+                        click_button(&mut commands, entity, &mut color_material, &mut button, control, MouseButton::Left, transform.translation.truncate(), &time, &audio, &asset_server, &mut button_clicked_events);
+                    },
+                    _ => { } // do nothing
+                }
+            }
+        }
+    }
+}
+
+fn get_focus_move(keyboard_input: &Input<KeyCode>, gamepad_input: &Input<GamepadButton>, gamepad_axes: &Axis<GamepadAxis>, gamepads: &Vec<Gamepad>, stick_engaged: &mut bool) -> Option<FocusMove> {
+    if keyboard_input.just_pressed(KeyCode::Tab) {
+        let shift = keyboard_input.pressed(KeyCode::LShift) || keyboard_input.pressed(KeyCode::RShift);
+        return if shift { Some(FocusMove::Previous) } else { Some(FocusMove::Next) };
+    }
+
+    if keyboard_input.just_pressed(KeyCode::Up) { return Some(FocusMove::Direction(Vec2::new(0.0, 1.0))); }
+    if keyboard_input.just_pressed(KeyCode::Down) { return Some(FocusMove::Direction(Vec2::new(0.0, -1.0))); }
+    if keyboard_input.just_pressed(KeyCode::Left) { return Some(FocusMove::Direction(Vec2::new(-1.0, 0.0))); }
+    if keyboard_input.just_pressed(KeyCode::Right) { return Some(FocusMove::Direction(Vec2::new(1.0, 0.0))); }
+
+    for button in gamepad_input.get_just_pressed() {
+        match button.1 {
+            GamepadButtonType::DPadUp => { return Some(FocusMove::Direction(Vec2::new(0.0, 1.0))); },
+            GamepadButtonType::DPadDown => { return Some(FocusMove::Direction(Vec2::new(0.0, -1.0))); },
+            GamepadButtonType::DPadLeft => { return Some(FocusMove::Direction(Vec2::new(-1.0, 0.0))); },
+            GamepadButtonType::DPadRight => { return Some(FocusMove::Direction(Vec2::new(1.0, 0.0))); },
+            _ => { }
+        }
+    }
+
+    // the stick has to go back to the middle before it moves the focus again
+    let mut stick = Vec2::ZERO;
+    for gamepad in gamepads.iter() {
+        let x = gamepad_axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+        let y = gamepad_axes.get(GamepadAxis(*gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0);
+        if x.abs() + y.abs() > stick.x.abs() + stick.y.abs() {
+            stick = Vec2::new(x, y);
+        }
+    }
+    if stick.length() < 0.5 {
+        *stick_engaged = false;
+    } else if !*stick_engaged {
+        *stick_engaged = true;
+        let direction = if stick.x.abs() > stick.y.abs() { Vec2::new(stick.x.signum(), 0.0) } else { Vec2::new(0.0, stick.y.signum()) };
+
+        return Some(FocusMove::Direction(direction));
+    }
+
+    None
+}
+
+fn get_next_focus(current: Option<Entity>, default_focus: Option<Entity>, focus_move: FocusMove, mut candidates: Vec<FocusCandidate>) -> Option<Entity> {
+    // tab order is by tab_index, then top to bottom and left to right
+    candidates.sort_by(|a, b| {
+        a.tab_index.cmp(&b.tab_index)
+            .then(b.position.y.partial_cmp(&a.position.y).unwrap_or(std::cmp::Ordering::Equal))
+            .then(a.position.x.partial_cmp(&b.position.x).unwrap_or(std::cmp::Ordering::Equal))
+    });
+
+    let current_index = candidates.iter().position(|candidate| Some(candidate.entity) == current);
+    let current_index = match current_index {
+        None => {
+            // nothing focused yet
+            let default_focus = candidates.iter().find(|candidate| Some(candidate.entity) == default_focus);
+            return default_focus.or(candidates.first()).map(|candidate| candidate.entity);
+        },
+        Some(index) => index
+    };
+    let navigation = candidates[current_index].navigation;
+    let last_index = candidates.len() - 1;
+
+    let result = match focus_move {
+        FocusMove::Next if current_index == last_index && !navigation.wrap => current,
+        FocusMove::Next => Some(candidates[(current_index + 1) % candidates.len()].entity),
+        FocusMove::Previous if current_index == 0 && !navigation.wrap => current,
+        FocusMove::Previous => Some(candidates[(current_index + last_index) % candidates.len()].entity),
+        FocusMove::Direction(direction) => {
+            // neighbours named in the file win over the nearest control
+            let neighbour = if direction.y > 0.0 {
+                navigation.up
+            } else if direction.y < 0.0 {
+                navigation.down
+            } else if direction.x < 0.0 {
+                navigation.left
+            } else {
+                navigation.right
+            };
+
+            // a neighbour that is hidden or disabled can't take the focus
+            let neighbour = neighbour.filter(|neighbour| candidates.iter().any(|candidate| candidate.entity == *neighbour));
+
+            let current_position = candidates[current_index].position;
+            let nearest = get_nearest_in_direction(&candidates, current_position, direction, false);
+            let wrapped = if navigation.wrap { get_nearest_in_direction(&candidates, current_position, direction, true) } else { None };
+
+            neighbour.or(nearest).or(wrapped).or(current)
+        }
+    };
+
+    result
+}
+
+// with wrap, looks for the control furthest away in the opposite direction instead
+fn get_nearest_in_direction(candidates: &Vec<FocusCandidate>, current_position: Vec2, direction: Vec2, wrap: bool) -> Option<Entity> {
+    let nearest = candidates
+        .iter()
+        .filter_map(|candidate| {
+            let distance = candidate.position - current_position;
+            let along = distance.dot(direction);
+            if (!wrap && along <= 0.0) || (wrap && along >= 0.0) { return None; }
+
+            // controls off to the side count as further away than controls straight ahead
+            let across = (distance - direction * along).length();
+
+            Some((candidate.entity, along + across * 2.0))
+        })
+        .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+
+    nearest.map(|(entity, _)| entity)
+}
+
+fn default_focus_system(
+    mut focus: ResMut<GergFocus>,
+    default_focus_query: Query<Entity, Added<DefaultFocus>>
+) {
+    // a newly spawned screen takes the focus if it has a default
+    if let Some(entity) = default_focus_query.iter().last() {
+        focus.entity = Some(entity);
+    }
+}
+
+fn focus_lost_system(
+    mut focus: ResMut<GergFocus>,
+    control_query: Query<(&Visible, &GergButton)>
+) {
+    // the focus goes when its button is destroyed, hidden or disabled
+    if let Some(entity) = focus.entity {
+        let focusable = match control_query.get(entity) {
+            Ok((visible, button)) => is_focusable(visible, button),
+            Err(_) => false
+        };
+        if !focusable {
+            focus.entity = None;
+        }
+    }
+}
+
+fn is_focusable(visible: &Visible, button: &GergButton) -> bool {
+    let result = visible.is_visible && !matches!(button.button_state, ButtonState::Disabled);
+
+    result
+}
+
+fn focus_highlight_system(
+    focus: Res<GergFocus>,
+    mut control_query: Query<(&mut Handle<ColorMaterial>, &mut GergButton)>
+) {
+    if let Some(entity) = focus.entity {
+        if let Ok((mut color_material, mut button)) = control_query.get_mut(entity) {
+            match button.button_state {
+                ButtonState::Normal => {
+                    // change to hover
+                    button.button_state = ButtonState::Hover;
+                    *color_material = button.color_material_handle_hover.clone()
+                },
+                _ => { } // do nothing
+            }
+        }
+    }
+}
+
 fn destroy_controls_system(
     mut commands: Commands,
     controls_to_be_destroyed_query: Query<Entity, With<DestroyControl>>
@@ -115,62 +1229,98 @@ fn destroy_controls_system(
     }
 }
 
-fn get_cursor_position(windows: Res<Windows>) -> Vec2 {
-    let window = windows.get_primary().expect("no primary window");
-    let screen_size = Vec2::new(window.width(), window.height());
+// None while the cursor is outside every window
+fn get_cursor_window_position(windows: &Windows) -> Option<(WindowId, Vec2)> {
+    let result = windows.iter().find_map(|window| window.cursor_position().map(|cursor_position| (window.id(), cursor_position)));
 
-    let cursor_position = match window.cursor_position() {
-        Some(cp) => cp,
-        None => Vec2::new(-1.0, -1.0),
-    };
+    result
+}
+
+// bevy measures touches from the bottom left (like the cursor) on Android and iOS, but from the top left everywhere else
+fn get_touch_window_position(windows: &Windows, touch_position: Vec2) -> Vec2 {
+    if cfg!(any(target_os = "android", target_os = "ios")) {
+        return touch_position;
+    }
 
-    let cursor_position = cursor_position - screen_size / 2.0;
+    let window_height = windows.get_primary().map_or(0.0, |window| window.height());
+    let result = Vec2::new(touch_position.x, window_height - touch_position.y);
 
-    cursor_position
+    result
+}
+
+// the shapes are tested in the control's own space, so they hold however it is scaled or rotated
+fn to_local_position(position: Vec2, transform: &GlobalTransform) -> Vec2 {
+    let local_position = transform.compute_matrix().inverse().transform_point3(position.extend(transform.translation.z));
+
+    local_position.truncate()
 }
 
-fn get_control_bounding_shapes(button_bounding_box: Vec4, button_bounding_circle: Vec3, sprite: &Sprite, transform: &Transform) -> (Option<Rect<f32>>, Option<Circle>) {
+fn cursor_position_overlaps_button(cursor_position: Vec2, button: &GergButton, sprite: &Sprite, transform: &GlobalTransform) -> bool {
+    let local_position = to_local_position(cursor_position, transform);
+    let control_bounding_shapes = get_control_bounding_shapes(button.bounding_box, button.bounding_circle, &button.bounding_polygon, sprite);
+
+    cursor_position_overlaps_control_bounding_shapes(local_position, control_bounding_shapes)
+}
+
+fn cursor_position_overlaps_sprite(cursor_position: Vec2, sprite: &Sprite, transform: &GlobalTransform) -> bool {
+    let local_position = to_local_position(cursor_position, transform);
+
+    cursor_position_overlaps_control_rect(local_position, &create_rectangle(0.0, 0.0, sprite.size.x, sprite.size.y))
+}
+
+// in the control's own space, centered on it
+fn get_control_bounding_shapes(button_bounding_box: Vec4, button_bounding_circle: Vec3, button_bounding_polygon: &Vec<Vec2>, sprite: &Sprite) -> (Option<Rect<f32>>, Option<Circle>, Option<Polygon>) {
+    let polygon = if button_bounding_polygon.is_empty() {
+        None
+    } else {
+        Some(Polygon { points: button_bounding_polygon.clone() })
+    };
+
     if button_bounding_box.x == 0.0 && button_bounding_box.y == 0.0 && button_bounding_box.z == 0.0 && button_bounding_box.w == 0.0 {
         if button_bounding_circle.x == 0.0 && button_bounding_circle.y == 0.0 && button_bounding_circle.z == 0.0 {
-            let rect = create_rectangle(button_bounding_box.x, button_bounding_box.y, sprite.size.x, sprite.size.y, transform);
+            if polygon.is_some() {
+                return (None, None, polygon);
+            }
 
-            return (Some(rect), None);
+            let rect = create_rectangle(button_bounding_box.x, button_bounding_box.y, sprite.size.x, sprite.size.y);
+
+            return (Some(rect), None, None);
         } else {
-            let circle = create_circle(transform, button_bounding_circle);
+            let circle = create_circle(button_bounding_circle);
 
-            return (None, Some(circle));
+            return (None, Some(circle), polygon);
         };
     } else {
         if button_bounding_circle.x == 0.0 && button_bounding_circle.y == 0.0 && button_bounding_circle.z == 0.0 {
-            let rect = create_rectangle(button_bounding_box.x, button_bounding_box.y, button_bounding_box.z, button_bounding_box.w, transform);
+            let rect = create_rectangle(button_bounding_box.x, button_bounding_box.y, button_bounding_box.z, button_bounding_box.w);
 
-            return (Some(rect), None);
+            return (Some(rect), None, polygon);
         } else {
-            let rect = create_rectangle(button_bounding_box.x, button_bounding_box.y, button_bounding_box.z, button_bounding_box.w, transform);
-            let circle = create_circle(transform, button_bounding_circle);
+            let rect = create_rectangle(button_bounding_box.x, button_bounding_box.y, button_bounding_box.z, button_bounding_box.w);
+            let circle = create_circle(button_bounding_circle);
 
-            return (Some(rect), Some(circle));
+            return (Some(rect), Some(circle), polygon);
         };
     };
 }
 
-fn create_rectangle(x: f32, y: f32, width: f32, height: f32, transform: &Transform) -> Rect<f32> {
-    let x = (transform.translation.x + x) - (width * 0.5);
-    let y = (transform.translation.y + y) + (height * 0.5);
+fn create_rectangle(x: f32, y: f32, width: f32, height: f32) -> Rect<f32> {
+    let x = x - (width * 0.5);
+    let y = y + (height * 0.5);
     let rect = Rect { left: x, right: x + width, top: y, bottom: y - height };
 
     rect
 }
 
-fn create_circle(transform: &Transform, button_bounding_circle: Vec3) -> Circle {
-    let center = Vec2::new(transform.translation.x + button_bounding_circle.x, transform.translation.y + button_bounding_circle.y);
+fn create_circle(button_bounding_circle: Vec3) -> Circle {
+    let center = Vec2::new(button_bounding_circle.x, button_bounding_circle.y);
     let radius = button_bounding_circle.z;
     let circle = Circle { center, radius };
 
     circle
 }
 
-fn cursor_position_overlaps_control_bounding_shapes(cursor_position: Vec2, bounding_shapes: (Option<Rect<f32>>, Option<Circle>)) -> bool {
+fn cursor_position_overlaps_control_bounding_shapes(cursor_position: Vec2, bounding_shapes: (Option<Rect<f32>>, Option<Circle>, Option<Polygon>)) -> bool {
     let rectangle_collides = match bounding_shapes.0 {
         Some(rect) => cursor_position_overlaps_control_rect(cursor_position, &rect),
         None => true,
@@ -181,7 +1331,12 @@ fn cursor_position_overlaps_control_bounding_shapes(cursor_position: Vec2, bound
         None => true,
     };
 
-    rectangle_collides && circle_collides
+    let polygon_collides = match bounding_shapes.2 {
+        Some(polygon) => cursor_position_overlaps_control_polygon(cursor_position, &polygon),
+        None => true,
+    };
+
+    rectangle_collides && circle_collides && polygon_collides
 }
 
 fn cursor_position_overlaps_control_rect(cursor_position: Vec2, control_bounding_box: &Rect<f32>) -> bool {
@@ -195,6 +1350,56 @@ fn cursor_position_overlaps_control_rect(cursor_position: Vec2, control_bounding
     result
 }
 
+// even-odd rule, so concave polygons work too
+pub(crate) fn cursor_position_overlaps_control_polygon(cursor_position: Vec2, control_bounding_polygon: &Polygon) -> bool {
+    let points = &control_bounding_polygon.points;
+    let mut inside = false;
+    for i in 0..points.len() {
+        let a = points[i];
+        let b = points[(i + 1) % points.len()];
+        if (a.y > cursor_position.y) != (b.y > cursor_position.y) {
+            let crossing_x = a.x + (cursor_position.y - a.y) / (b.y - a.y) * (b.x - a.x);
+            if cursor_position.x < crossing_x {
+                inside = !inside;
+            }
+        }
+    }
+
+    inside
+}
+
+// whether the texture is opaque enough under the cursor, textures that aren't loaded yet (or have no alpha) count as opaque
+fn cursor_position_overlaps_control_alpha(local_position: Vec2, alpha_mask: Option<&AlphaMask>, alpha_threshold: f32, sprite: &Sprite) -> bool {
+    let alpha_mask = match alpha_mask {
+        Some(alpha_mask) => alpha_mask,
+        None => return true
+    };
+
+    // textures are stored from the top row down
+    let u = local_position.x / sprite.size.x + 0.5;
+    let v = 0.5 - local_position.y / sprite.size.y;
+    if u < 0.0 || u >= 1.0 || v < 0.0 || v >= 1.0 { return false; }
+
+    let x = (u * alpha_mask.width as f32) as usize;
+    let y = (v * alpha_mask.height as f32) as usize;
+    let alpha = alpha_mask.alpha[y * alpha_mask.width + x] as f32 / 255.0;
+
+    alpha >= alpha_threshold
+}
+
+fn create_alpha_mask(texture: &Texture) -> Option<AlphaMask> {
+    let has_alpha = matches!(texture.format, TextureFormat::Rgba8Unorm | TextureFormat::Rgba8UnormSrgb | TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb);
+    if !has_alpha { return None; }
+
+    let alpha_mask = AlphaMask {
+        width: texture.size.width as usize,
+        height: texture.size.height as usize,
+        alpha: texture.data.iter().skip(3).step_by(4).copied().collect()
+    };
+
+    Some(alpha_mask)
+}
+
 fn cursor_position_overlaps_control_circle(cursor_position: Vec2, control_bounding_circle: &Circle) -> bool {
     let distance = control_bounding_circle.center - cursor_position;
     let length = distance.length();
@@ -203,11 +1408,210 @@ fn cursor_position_overlaps_control_circle(cursor_position: Vec2, control_boundi
     result
 }
 
+/// Overrides the safe_area of every control group's global settings while set, e.g. to keep clear of TV overscan.
+#[derive(Default)]
+pub struct SafeArea {
+    pub margins: Option<Rect<Val>>
+}
+
+/// The topmost control under the cursor and under each finger, found once per frame in `CoreStage::PreUpdate`.
+#[derive(Default)]
+pub struct PointerTargets {
+    pub cursor: Option<PointerTarget>, // None while the cursor is outside the window
+    pub touches: HashMap<u64, PointerTarget>,
+    pub drags: HashMap<u64, TouchDrag> // one per finger that is down, or was lifted this frame
+}
+
+#[derive(Clone, Copy)]
+pub struct PointerTarget {
+    pub position: Vec2, // in the world of the camera that draws the target (relative to the middle of the window without one)
+    pub entity: Option<Entity>
+}
+
+// positions are in the world of the camera that draws the control the finger went down on (relative to the middle of the window without one)
+#[derive(Clone)]
+pub struct TouchDrag {
+    pub entity: Option<Entity>, // the control the finger went down on, even after the finger has moved off it
+    pub group_name: Option<String>,
+    pub start_position: Vec2,
+    pub position: Vec2,
+    pub delta: Vec2 // moved since the last frame
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub(crate) struct GroupCamera {
+    window_id: WindowId,
+    window_size: Vec2,
+    world_from_ndc: Mat4
+}
+
+/// Whether gerg_ui used this frame's input, updated in `CoreStage::PreUpdate` so that game systems can ignore what the UI consumed.
+#[derive(Default)]
+pub struct UiInputState {
+    pub pointer_over_control: bool,
+    pub click_consumed: bool,
+    pub keyboard_consumed: bool,
+    pub scroll_consumed: bool
+}
+
+/// Labels for ordering game systems around gerg_ui's, e.g. `.after(GergUiSystem::Input)`.
+#[derive(SystemLabel, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum GergUiSystem {
+    PointerTargets, // updates PointerTargets (in CoreStage::PreUpdate)
+    InputState, // updates UiInputState (in CoreStage::PreUpdate)
+    Input // handles clicks, touches, hover and focus (in CoreStage::Update)
+}
+
+/// The control that keyboard and gamepad input goes to, if any.
+#[derive(Default)]
+pub struct GergFocus {
+    pub entity: Option<Entity>
+}
+
+enum FocusMove {
+    Next,
+    Previous,
+    Direction(Vec2)
+}
+
+struct FocusCandidate {
+    entity: Entity,
+    position: Vec2,
+    tab_index: i32,
+    navigation: FocusNavigation
+}
+
+#[derive(Clone, Copy)]
+pub struct FocusNavigation {
+    pub up: Option<Entity>,
+    pub down: Option<Entity>,
+    pub left: Option<Entity>,
+    pub right: Option<Entity>,
+    pub wrap: bool
+}
+impl Default for FocusNavigation {
+    fn default() -> Self { FocusNavigation { up: None, down: None, left: None, right: None, wrap: true } }
+}
+
+pub struct DefaultFocus;
+
+pub struct BlocksInput;
+
+struct AlphaMask {
+    width: usize,
+    height: usize,
+    alpha: Vec<u8>
+}
+
 pub struct Cooldown {
-    pub remaining_time_in_seconds: f32
+    pub remaining_time_in_seconds: f32, // until the button can be clicked again
+    pub remaining_active_time_in_seconds: f32 // until the button stops showing its active texture
+}
+
+pub struct ButtonPress {
+    pub pointer: Pointer,
+    pub held_time_in_seconds: f32,
+    pub repeat_count: u32,
+    pub click_on_release: bool,
+    pub long_pressed: bool
+}
+impl ButtonPress {
+    fn new(pointer: Pointer, click_on_release: bool) -> Self {
+        ButtonPress { pointer, held_time_in_seconds: 0.0, repeat_count: 0, click_on_release, long_pressed: false }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Pointer {
+    Mouse(MouseButton),
+    Touch(u64)
+}
+
+/// Named callbacks that buttons can run with `on_click: call(name)`.
+#[derive(Default)]
+pub struct ActionRegistry {
+    actions: HashMap<String, Box<dyn Fn(&mut Commands, &GergButtonClicked) + Send + Sync>>
+}
+impl ActionRegistry {
+    pub fn register(&mut self, name: &str, action: impl Fn(&mut Commands, &GergButtonClicked) + Send + Sync + 'static) {
+        self.actions.insert(name.to_string(), Box::new(action));
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ClickAction {
+    CloseGroup(String),
+    OpenScreen(String),
+    Emit(String),
+    Toggle(String),
+    Call(String)
+}
+
+/// Enables or disables a button, e.g. `SetButtonEnabled::named("buy_button", "shop.ui", false)`.
+pub struct SetButtonEnabled {
+    pub target: ButtonTarget,
+    pub enabled: bool
+}
+impl SetButtonEnabled {
+    pub fn entity(entity: Entity, enabled: bool) -> Self {
+        SetButtonEnabled { target: ButtonTarget::Entity(entity), enabled }
+    }
+
+    pub fn named(name: &str, group_name: &str, enabled: bool) -> Self {
+        SetButtonEnabled { target: ButtonTarget::Name { name: name.to_string(), group_name: group_name.to_string() }, enabled }
+    }
+}
+
+pub enum ButtonTarget {
+    Entity(Entity),
+    Name { name: String, group_name: String }
+}
+
+pub struct GergActionEmitted {
+    pub name: String,
+    pub entity: Entity,
+    pub group_name: String
+}
+
+struct ClickMarkers {
+    enabled: bool
+}
+
+pub struct GergButtonClicked {
+    pub entity: Entity,
+    pub name: String,
+    pub group_name: String,
+    pub mouse_button: MouseButton, // touches and keyboard/gamepad activation count as MouseButton::Left
+    pub position: Vec2, // where the pointer was, or the button's position for keyboard/gamepad activation
+    pub double_click: bool
+}
+
+pub struct ControlHoverEntered {
+    pub entity: Entity,
+    pub name: String,
+    pub group_name: String
+}
+
+pub struct ControlHoverExited {
+    pub entity: Entity,
+    pub name: String,
+    pub group_name: String
+}
+
+pub struct GergButtonLongPressed {
+    pub entity: Entity,
+    pub name: String,
+    pub group_name: String,
+    pub position: Vec2
 }
 
-pub struct ButtonClicked;
+// marker versions of the events, only inserted with ControlsPlugin::with_click_markers()
+pub struct ButtonClicked {
+    pub mouse_button: MouseButton, // touches and keyboard/gamepad activation count as MouseButton::Left
+    pub double_click: bool
+}
+
+pub struct ButtonLongPressed;
 
 pub struct DestroyControl;
 
@@ -217,6 +1621,16 @@ pub struct GergControl {
     pub group_name: String
 }
 
+pub struct GergControlGroup {
+    pub(crate) controls: Controls,
+    pub(crate) entities: HashMap<String, Entity>,
+    pub(crate) screen_size: Vec2,
+    pub(crate) scale: f32,
+    pub(crate) layout_changed: bool,
+    pub(crate) window: WindowId,
+    pub(crate) camera: Option<GroupCamera>
+}
+
 pub struct GergPictureBox {
     pub name: String
 }
@@ -225,6 +1639,20 @@ pub struct GergLabel {
     pub name: String
 }
 
+pub struct AutoSize;
+
+pub struct LabelLayout {
+    pub top_left_position: Vec2,
+    pub size: Vec2,
+    pub screen_size: Vec2,
+    pub horizontal_align: HorizontalAlign,
+    pub vertical_align: VerticalAlign,
+    pub font_size: f32,
+    pub wrap_size: Option<Vec2>,
+    pub scale: f32,
+    pub parent_position: Option<Vec3>
+}
+
 pub struct GergButton {
     pub name: String,
     pub button_state: ButtonState,
@@ -233,13 +1661,40 @@ pub struct GergButton {
     pub color_material_handle_active: Handle<ColorMaterial>,
     pub color_material_handle_disabled: Handle<ColorMaterial>,
     pub on_click_sound: String,
+    pub on_hover_sound: String,
+    pub hovered: bool, // whether the cursor is over the button, whatever its state
     pub bounding_box: Vec4,
     pub bounding_circle: Vec3,
+    pub bounding_polygon: Vec<Vec2>,
+    pub hit_test: HitTest,
+    pub alpha_threshold: f32, // 0..1, only used with HitTest::Alpha
+    pub tab_index: i32,
+    pub on_click_button: Option<MouseButton>, // None for any
+    pub double_click_time: f32,
+    pub last_click: Option<(MouseButton, f64)>,
+    pub activate_on: ActivateOn,
+    pub repeat_delay: f32,
+    pub repeat_interval: f32, // 0 for no repeats
+    pub long_press_time: f32, // 0 for no long press
+    pub on_click: Vec<ClickAction>,
+    pub cooldown: f32,
+    pub active_display_time: f32
 }
 
 pub enum ButtonState {
     Normal,
     Hover,
+    Pressed,
     Active,
     Disabled
 }
+
+pub enum HitTest {
+    Shape, // bounding_box, bounding_circle and bounding_polygon
+    Alpha // the shapes, and then the alpha of texture_name_normal
+}
+
+pub enum ActivateOn {
+    Press,
+    Release
+}
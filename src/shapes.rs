@@ -4,3 +4,7 @@ pub struct Circle {
     pub center: Vec2,
     pub radius: f32
 }
+
+pub struct Polygon {
+    pub points: Vec<Vec2>
+}